@@ -0,0 +1,232 @@
+//! Rate limits shared by the pv processes on one host.
+//!
+//! A group is a token bucket kept in a file, which members take from under a
+//! lock, so together they send no faster than the group's limit, and a member
+//! with little to send leaves the rest to the others. Each member also holds a
+//! shared lock on a second file for as long as it runs, which goes however it
+//! exits, so the first to join an empty group can tell and set the limit.
+//! Groups belong to one user, and live in a directory only they can get into.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct RateGroup {
+    /// Holds the unit, the limit, the tokens left, and when they were counted
+    bucket: File,
+    /// Locked by every member for as long as it's in the group
+    members: File,
+    /// What the group counts, bytes or lines
+    unit: &'static str,
+    /// Units a second, for the whole group
+    limit: f64,
+    capacity: f64,
+    /// Units already taken from the bucket but not yet sent
+    reserved: f64,
+}
+
+impl RateGroup {
+    /// Join the group called `name`, which must have a limit of `limit` `unit`s unless it's empty
+    pub fn join(name: &str, limit: f64, unit: &'static str) -> io::Result<RateGroup> {
+        // Anything but letters and digits is spelled out in hex, so no two names share files
+        let name: String = name
+            .bytes()
+            .map(|byte| match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (byte as char).to_string(),
+                _ => format!("_{byte:02x}"),
+            })
+            .collect();
+        let dir = groups_dir()?;
+        let group = RateGroup {
+            bucket: open_private(&dir.join(format!("{name}.bucket")))?,
+            members: open_private(&dir.join(format!("{name}.members")))?,
+            unit,
+            limit,
+            // A tenth of a second's worth, like a member's own bucket
+            capacity: (limit / 10.0).max(1.0),
+            reserved: 0.0,
+        };
+        group.locked(|| {
+            // Nobody else holds the members' lock once the group is empty
+            let alone = flock(&group.members, Lock::Exclusive, false)?;
+            match group.read()? {
+                Some((unit, limit, _, _))
+                    if !alone && (unit != group.unit || limit != group.limit) =>
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "the group's rate limit is {} {} a second, not {} {}",
+                            limit, unit, group.limit, group.unit
+                        ),
+                    ));
+                }
+                Some(_) if !alone => (),
+                _ => group.write(group.capacity, now())?,
+            }
+            flock(&group.members, Lock::Shared, true).map(drop)
+        })?;
+        Ok(group)
+    }
+
+    /// Take `units` from the group's bucket, returning how long to wait before sending them
+    pub fn take(&mut self, units: u64) -> io::Result<Duration> {
+        let needed = units as f64 - self.reserved;
+        if needed <= 0.0 {
+            self.reserved = -needed;
+            return Ok(Duration::ZERO);
+        }
+        // Small takes, like a line at a time, come out of a bucketful taken ahead,
+        // rather than locking the bucket for every one
+        let units = needed.max(self.capacity);
+        let wait = self.locked(|| {
+            let now = now();
+            let (tokens, counted) = match self.read()? {
+                Some((_, _, tokens, counted)) => (tokens, counted),
+                None => (self.capacity, now),
+            };
+            let refill = (now - counted).max(0.0) * self.limit;
+            let tokens = (tokens + refill).min(self.capacity) - units;
+            self.write(tokens, now)?;
+            Ok(if tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-tokens / self.limit)
+            })
+        })?;
+        self.reserved = units - needed;
+        Ok(wait)
+    }
+
+    /// Run `f` with the bucket to this member alone
+    fn locked<T>(&self, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        flock(&self.bucket, Lock::Exclusive, true)?;
+        let result = f();
+        flock(&self.bucket, Lock::Unlocked, true)?;
+        result
+    }
+
+    /// The unit, limit, tokens and time in the bucket, or None if it hasn't been filled yet
+    fn read(&self) -> io::Result<Option<(String, f64, f64, f64)>> {
+        let mut text = String::new();
+        let mut bucket = &self.bucket;
+        bucket.seek(SeekFrom::Start(0))?;
+        bucket.read_to_string(&mut text)?;
+        let mut fields = text.split_whitespace();
+        let unit = fields.next().unwrap_or("").to_string();
+        let numbers: Vec<f64> = fields.filter_map(|field| field.parse().ok()).collect();
+        Ok(match numbers[..] {
+            [limit, tokens, counted] => Some((unit, limit, tokens, counted)),
+            _ => None,
+        })
+    }
+
+    fn write(&self, tokens: f64, counted: f64) -> io::Result<()> {
+        let mut bucket = &self.bucket;
+        bucket.set_len(0)?;
+        bucket.seek(SeekFrom::Start(0))?;
+        bucket
+            .write_all(format!("{} {} {} {}\n", self.unit, self.limit, tokens, counted).as_bytes())
+    }
+}
+
+/// Where this user's groups go, made private to them
+#[cfg(unix)]
+fn groups_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    let uid = unsafe { libc::getuid() };
+    let root = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|root| root.is_absolute())
+        .unwrap_or_else(std::env::temp_dir);
+    let dir = root.join(format!("pv-rate-groups-{uid}"));
+    create_private_dir(&dir)?;
+    // Someone else may have made it first, to see or tamper with the groups
+    let meta = fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("'{}' isn't a directory private to this user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Where this user's groups go, which the temporary directory already keeps private
+#[cfg(not(unix))]
+fn groups_dir() -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("pv-rate-groups");
+    create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Make a directory no one else can get into, unless it's already there
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    match builder.create(dir) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+        created => created,
+    }
+}
+
+/// Open one of a group's files, never through a symlink
+fn open_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    options.open(path)
+}
+
+enum Lock {
+    Shared,
+    Exclusive,
+    Unlocked,
+}
+
+/// Lock `file` against other processes, returning false if it's taken and `wait` is false
+#[cfg(unix)]
+fn flock(file: &File, lock: Lock, wait: bool) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let mut operation = match lock {
+        Lock::Shared => libc::LOCK_SH,
+        Lock::Exclusive => libc::LOCK_EX,
+        Lock::Unlocked => libc::LOCK_UN,
+    };
+    if !wait {
+        operation |= libc::LOCK_NB;
+    }
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(true);
+        }
+        let e = io::Error::last_os_error();
+        match e.kind() {
+            io::ErrorKind::Interrupted => continue,
+            io::ErrorKind::WouldBlock if !wait => return Ok(false),
+            _ => return Err(e),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn flock(_file: &File, _lock: Lock, _wait: bool) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "rate groups are only supported on Unix",
+    ))
+}
+
+/// Seconds since the epoch, which unlike an Instant means the same to every member
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
@@ -0,0 +1,297 @@
+//! Rate limiting with a token bucket
+
+use crate::group::RateGroup;
+use crate::pressure::Pressure;
+use crate::schedule::Schedule;
+use std::time::{Duration, Instant};
+
+/// How often the rate is looked at again, for limits that change over time
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lets `rate` units a second through on average, and up to `burst` at once
+pub struct TokenBucket {
+    /// None when unlimited
+    rate: Option<f64>,
+    /// Set with --burst
+    burst: Option<f64>,
+    capacity: f64,
+    /// Units that may go without waiting, or negative once in debt
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// Start with a full bucket, holding a tenth of a second's worth unless `burst` is given
+    pub fn new(rate: Option<f64>, burst: Option<f64>) -> TokenBucket {
+        let mut bucket = TokenBucket {
+            rate: None,
+            burst,
+            capacity: 1.0,
+            tokens: 0.0,
+            refilled: Instant::now(),
+        };
+        bucket.set_rate(rate);
+        bucket
+    }
+
+    /// Change the rate, keeping what has built up so far
+    pub fn set_rate(&mut self, rate: Option<f64>) {
+        if rate == self.rate {
+            return;
+        }
+        // Take the rate to have moved steadily from the old one, as it does on a ramp
+        if let (Some(old), Some(new)) = (self.rate, rate) {
+            self.refill((old + new) / 2.0);
+        }
+        self.refilled = Instant::now();
+        self.capacity = match rate {
+            Some(rate) => self.burst.unwrap_or(rate / 10.0).max(1.0),
+            None => 1.0,
+        };
+        // Coming off unlimited starts with a full bucket
+        self.tokens = match self.rate {
+            Some(_) => self.tokens.min(self.capacity),
+            None => self.capacity,
+        };
+        self.rate = rate;
+    }
+
+    /// The most to write at once, so a big buffer goes out smoothly rather than all together
+    pub fn slice(&self) -> usize {
+        match self.rate {
+            Some(rate) => (self.capacity.min(rate / 10.0) as usize).max(1),
+            None => usize::MAX,
+        }
+    }
+
+    /// Take `units` from the bucket, returning how long to wait before sending them
+    pub fn take(&mut self, units: u64) -> Duration {
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+        self.refill(rate);
+        self.tokens -= units as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+
+    /// Units owed for what was sent ahead of the rate
+    pub fn debt(&self) -> f64 {
+        (-self.tokens).max(0.0)
+    }
+
+    /// Add what `rate` earned since the last refill
+    fn refill(&mut self, rate: f64) {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled).as_secs_f64() * rate;
+        // Time spent stalled only earns a burst, not a catch-up at any speed
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.refilled = now;
+    }
+}
+
+/// How many times an exponential ramp doubles the rate on its way up
+const RAMP_DOUBLINGS: f64 = 10.0;
+
+/// How --ramp raises the rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RampShape {
+    /// By the same amount every second
+    Linear,
+    /// Doubling at a steady pace, so slowly at first
+    Exponential,
+}
+
+/// Raises the rate from zero to the full limit over `duration`
+pub struct Ramp {
+    duration: Duration,
+    shape: RampShape,
+    /// When the first data went out, since the ramp starts with the transfer
+    start: Option<Instant>,
+}
+
+impl Ramp {
+    pub fn new(duration: Duration, shape: RampShape) -> Ramp {
+        Ramp {
+            duration,
+            shape,
+            start: None,
+        }
+    }
+
+    /// The share of the full rate `elapsed` into the ramp
+    fn fraction(&self, elapsed: Duration) -> f64 {
+        let progress = (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0);
+        match self.shape {
+            RampShape::Linear => progress,
+            RampShape::Exponential => {
+                (2f64.powf(RAMP_DOUBLINGS * progress) - 1.0) / (2f64.powf(RAMP_DOUBLINGS) - 1.0)
+            }
+        }
+    }
+
+    /// Start the ramp, unless it already has
+    fn begin(&mut self) {
+        self.start.get_or_insert_with(Instant::now);
+    }
+
+    /// How far into the ramp it is, which is nowhere until it starts
+    fn elapsed(&self) -> Duration {
+        self.start.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    fn finished(&self) -> bool {
+        self.elapsed() >= self.duration
+    }
+
+    /// How long until the rising rate has paid off `debt` units
+    fn wait_for(&self, debt: f64, full_rate: f64) -> Duration {
+        let step = Duration::from_millis(10);
+        let now = self.elapsed();
+        let (mut at, mut paid) = (now, 0.0);
+        while paid < debt && at < self.duration {
+            paid += full_rate * self.fraction(at + step / 2) * step.as_secs_f64();
+            at += step;
+        }
+        let rest = Duration::from_secs_f64((debt - paid).max(0.0) / full_rate);
+        (at + rest).saturating_sub(now)
+    }
+}
+
+/// The least share of the rate limit that pressure can bring it down to
+const MIN_ADAPTIVE_FACTOR: f64 = 0.01;
+/// How much of the rate limit comes back each second once the pressure is off
+const ADAPTIVE_RECOVERY: f64 = 0.1;
+
+/// Backs the rate limit off while the system is under pressure
+pub struct Adaptive {
+    pressure: Pressure,
+    threshold: f64,
+    /// The share of the rate limit allowed now
+    factor: f64,
+}
+
+impl Adaptive {
+    pub fn new(pressure: Pressure, threshold: f64) -> Adaptive {
+        Adaptive {
+            pressure,
+            threshold,
+            factor: 1.0,
+        }
+    }
+
+    /// Halve the rate under pressure, and raise it steadily once the pressure is off
+    fn adjust(&mut self) {
+        self.factor = if self.pressure.latest() > self.threshold {
+            (self.factor / 2.0).max(MIN_ADAPTIVE_FACTOR)
+        } else {
+            (self.factor + ADAPTIVE_RECOVERY).min(1.0)
+        };
+    }
+}
+
+/// The rate limit in force, from -L and --rate-schedule, and shared with --rate-group
+pub struct Limiter {
+    bucket: TokenBucket,
+    /// Set with -L, for when no scheduled rule applies
+    base: Option<f64>,
+    schedule: Option<Schedule<f64>>,
+    group: Option<RateGroup>,
+    ramp: Option<Ramp>,
+    adaptive: Option<Adaptive>,
+    /// The full rate as of the last check, before any ramp
+    target: Option<f64>,
+    /// When to look at the rate again
+    recheck: Instant,
+}
+
+impl Limiter {
+    pub fn new(
+        base: Option<f64>,
+        burst: Option<f64>,
+        schedule: Option<Schedule<f64>>,
+        group: Option<RateGroup>,
+        ramp: Option<Ramp>,
+        adaptive: Option<Adaptive>,
+    ) -> Limiter {
+        let mut limiter = Limiter {
+            bucket: TokenBucket::new(None, burst),
+            base: base.filter(|&rate| rate > 0.0),
+            schedule,
+            group,
+            ramp,
+            adaptive,
+            target: None,
+            recheck: Instant::now(),
+        };
+        limiter.target = limiter.full_rate();
+        limiter.bucket.set_rate(limiter.ramped());
+        limiter
+    }
+
+    /// The limit now, in units a second, or None when unlimited
+    pub fn rate(&self) -> Option<f64> {
+        self.bucket.rate
+    }
+
+    /// The most to write at once
+    pub fn slice(&self) -> usize {
+        self.bucket.slice()
+    }
+
+    /// Take `units`, returning how long to wait before sending them
+    pub fn take(&mut self, units: u64) -> Duration {
+        if let Some(ref mut ramp) = self.ramp {
+            ramp.begin();
+        }
+        if self.recheck.elapsed() >= RECHECK_INTERVAL {
+            self.recheck = Instant::now();
+            if let Some(ref mut adaptive) = self.adaptive {
+                adaptive.adjust();
+            }
+            self.target = self.full_rate();
+        }
+        self.bucket.set_rate(self.ramped());
+        let mut wait = self.bucket.take(units);
+        // The rate rises while waiting on a ramp, so the debt is paid off sooner
+        let ramp = self.ramp.as_ref().filter(|ramp| !ramp.finished());
+        if let (Some(ramp), Some(target)) = (ramp, self.target) {
+            if !wait.is_zero() {
+                wait = ramp.wait_for(self.bucket.debt(), target);
+            }
+        }
+        // The whole group has to keep within its limit too
+        if let Some(ref mut group) = self.group {
+            // This member's own limit still holds if the group's files can't be used
+            wait = wait.max(group.take(units).unwrap_or_default());
+        }
+        wait
+    }
+
+    /// The rate now, part way up any ramp
+    fn ramped(&self) -> Option<f64> {
+        match self.ramp {
+            Some(ref ramp) if !ramp.finished() => self.target.map(|target| {
+                // Never quite zero, so the bucket can still count what it owes
+                (target * ramp.fraction(ramp.elapsed())).max(target * 1e-6)
+            }),
+            _ => self.target,
+        }
+    }
+
+    /// The rate that should apply now, before any ramp
+    fn full_rate(&self) -> Option<f64> {
+        let scheduled = self.schedule.as_ref().and_then(Schedule::rate_now);
+        let rate = match scheduled {
+            Some(rate) => rate.copied().filter(|&rate| rate > 0.0),
+            None => self.base,
+        };
+        match self.adaptive {
+            Some(ref adaptive) => rate.map(|rate| rate * adaptive.factor),
+            None => rate,
+        }
+    }
+}
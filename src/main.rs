@@ -1,44 +1,170 @@
-use clap::Parser;
-use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use clap::{CommandFactory, Parser};
+use indicatif::{
+    FormattedDuration, HumanBytes, HumanCount, HumanFloatCount, ProgressBar, ProgressDrawTarget,
+    ProgressStyle,
+};
 use std::fs::File;
 use std::io;
-use std::io::{ErrorKind, Read, Write};
-use std::time::Duration;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod group;
+mod limit;
+mod metrics;
+mod notify;
+mod output;
+mod pressure;
+mod records;
+mod report;
+mod schedule;
+mod statsd;
+mod trace;
+
+use group::RateGroup;
+use limit::{Adaptive, Limiter, Ramp, RampShape};
+use metrics::Metrics;
+use notify::Notifier;
+use output::{is_terminal, log_timestamp, BarTerm, LogTerm, ProgressOutput};
+use pressure::{Pressure, PressureSource, PSI_IO};
+use records::{parse_delimiter, Delimiter, RecordCounter, RecordFormat};
+use report::Snapshot;
+use schedule::Schedule;
+use statsd::StatsD;
+use trace::{Chunk, Replay, TraceFormat, Tracer};
 
 const DEFAULT_BUF_SIZE: usize = 65536;
+/// Exit status when --stall-timeout gives up, as timeout(1) uses
+const STALL_EXIT_STATUS: i32 = 124;
+
+/// A rate or size from the command line, before -k, -8 and -l settle what it counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Amount {
+    value: f64,
+    /// How many prefix steps up, so 2 for M or Mi
+    power: i32,
+    /// 1000 or 1024 when the prefix says which, as in kB or KiB
+    base: Option<f64>,
+    /// Whether the amount is in bits, when it says
+    bits: Option<bool>,
+}
 
-fn parse_rate_limit(s: &str) -> Result<u64, String> {
+impl Amount {
+    /// Bytes (or lines) for the display's units: bits with -8, and 1000s with -k
+    fn resolve(&self, si_units: bool, bits_mode: bool, counts_lines: bool) -> Result<f64, String> {
+        if counts_lines && self.bits.is_some() {
+            return Err("B and bit suffixes don't apply when counting lines".to_string());
+        }
+        let base = self.base.unwrap_or(if si_units { 1000.0 } else { 1024.0 });
+        let bits = self.bits.unwrap_or(bits_mode && !counts_lines);
+        let amount = self.value * base.powi(self.power);
+        Ok(if bits { amount / 8.0 } else { amount })
+    }
+}
+
+fn parse_rate_limit(s: &str) -> Result<Amount, String> {
     let s = s.trim();
     if s.is_empty() {
         return Err("Rate limit cannot be empty".to_string());
     }
 
-    let (number_part, suffix) = if let Some(last_char) = s.chars().last() {
-        if last_char.is_ascii_alphabetic() {
-            (&s[..s.len() - 1], last_char.to_ascii_lowercase())
-        } else {
-            (s, '\0')
+    let number_end = s
+        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .unwrap_or(s.len());
+    let (number_part, suffix) = s.split_at(number_end);
+    let value: f64 = number_part
+        .parse()
+        .map_err(|_| format!("Invalid number: {s}"))?;
+
+    // An optional prefix, then an optional unit, as in 12.5M, 100Mbit or 2MiB/s
+    let suffix = suffix.strip_suffix("/s").unwrap_or(suffix);
+    let (power, unit) = match suffix.chars().next().map(|ch| ch.to_ascii_lowercase()) {
+        Some('k') => (1, &suffix[1..]),
+        Some('m') => (2, &suffix[1..]),
+        Some('g') => (3, &suffix[1..]),
+        Some('t') => (4, &suffix[1..]),
+        _ => (0, suffix),
+    };
+    let (iec, unit) = match unit.strip_prefix('i') {
+        Some(unit) if power > 0 => (true, unit),
+        _ => (false, unit),
+    };
+    let bits = match unit {
+        "" => None,
+        "B" => Some(false),
+        "b" | "bit" | "bits" => Some(true),
+        _ => {
+            return Err(format!(
+                "Invalid suffix: {suffix}. Use k, m, g or t, optionally followed by i, B or bit"
+            ))
         }
+    };
+    // Prefixes on a plain number follow -k, but kB and Mbit are SI as usual
+    let base = if iec {
+        Some(1024.0)
+    } else if power > 0 && bits.is_some() {
+        Some(1000.0)
     } else {
-        (s, '\0')
+        None
     };
 
-    let base_rate: u64 = number_part
-        .parse()
-        .map_err(|_| format!("Invalid number: {number_part}"))?;
-
-    let multiplier = match suffix {
-        '\0' => 1,
-        'k' => 1024,
-        'm' => 1024 * 1024,
-        'g' => 1024 * 1024 * 1024,
-        't' => 1024_u64.pow(4),
-        _ => return Err(format!("Invalid suffix: {suffix}. Use k, m, g, or t")),
+    let amount = Amount {
+        value,
+        power,
+        base,
+        bits,
     };
+    if amount.resolve(false, false, false)?.is_finite() {
+        Ok(amount)
+    } else {
+        Err("Rate limit too large".to_string())
+    }
+}
 
-    base_rate
-        .checked_mul(multiplier)
-        .ok_or_else(|| "Rate limit too large".to_string())
+/// Rules given inline, or `@FILE` for a file holding them
+fn parse_rate_schedule(s: &str) -> Result<Schedule<Amount>, String> {
+    match s.strip_prefix('@') {
+        Some(path) => {
+            let rules = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read '{path}': {e}"))?;
+            Schedule::parse(&rules, parse_rate_limit)
+        }
+        None => Schedule::parse(s, parse_rate_limit),
+    }
+}
+
+fn parse_interval(s: &str) -> Result<f64, String> {
+    let seconds: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid number: {s}"))?;
+    if !(seconds.is_finite() && seconds > 0.0) {
+        return Err("Interval must be a positive number of seconds".to_string());
+    }
+    // Every interval ends up as a Duration, which has a limit
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(_) => Ok(seconds),
+        Err(_) => Err("Interval is too long".to_string()),
+    }
+}
+
+/// A file descriptor to write progress to
+fn parse_progress_fd(s: &str) -> Result<i32, String> {
+    match s.trim().parse() {
+        Ok(0) => Err("File descriptor 0 is the input, so progress can't go there".to_string()),
+        Ok(fd) if fd > 0 => Ok(fd),
+        _ => Err(format!("Invalid file descriptor: {s}")),
+    }
+}
+
+/// Like an interval, but 0 means none at all
+fn parse_delay(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(0.0) => Ok(0.0),
+        _ => parse_interval(s),
+    }
 }
 
 fn format_units(value: u64, use_si_units: bool, bits_mode: bool) -> String {
@@ -88,7 +214,38 @@ fn format_units(value: u64, use_si_units: bool, bits_mode: bool) -> String {
     }
 }
 
+/// Show data in the units -k and -8 ask for, rather than indicatif's binary bytes
+fn add_unit_keys(style: ProgressStyle, conf: &PipeViewConfig) -> ProgressStyle {
+    let (si_units, bits_mode) = (conf.si_units, conf.bits_mode);
+    if !(si_units || bits_mode) {
+        return style;
+    }
+    style
+        .with_key(
+            "bytes",
+            move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                let _ = write!(w, "{}", format_units(state.pos(), si_units, bits_mode));
+            },
+        )
+        .with_key(
+            "total_bytes",
+            move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                let len = state.len().unwrap_or(0);
+                let _ = write!(w, "{}", format_units(len, si_units, bits_mode));
+            },
+        )
+        .with_key(
+            "bytes_per_sec",
+            move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                let rate = state.per_sec() as u64;
+                let _ = write!(w, "{}/s", format_units(rate, si_units, bits_mode));
+            },
+        )
+}
+
 #[derive(Parser, Debug)]
+#[command(group = clap::ArgGroup::new("limits").multiple(true).args(["rate_limit", "rate_schedule"]))]
+#[command(group = clap::ArgGroup::new("line_modes").multiple(true).args(["line_mode", "delimiter", "records"]))]
 struct PipeViewConfig {
     /// Set estimated data size to SIZE bytes
     #[arg(short = 's')]
@@ -118,8 +275,17 @@ struct PipeViewConfig {
     #[arg(short = 'l')]
     line_mode: bool,
     /// Lines are null-terminated
-    #[arg(short = '0')]
+    #[arg(short = '0', conflicts_with_all = ["delimiter", "records"])]
     null: bool,
+    /// Count records ending in this delimiter instead of lines (implies -l). Accepts \n, \r, \t, \0, \\ and \xHH escapes
+    #[arg(long = "delimiter", value_parser = parse_delimiter, conflicts_with = "records")]
+    delimiter: Option<Delimiter>,
+    /// Count whole records of this format instead of lines (implies -l)
+    #[arg(long = "records", value_enum)]
+    records: Option<RecordFormat>,
+    /// In line mode, count the lines of the input files in a background scan to size the progressbar, unless -s gives the size
+    #[arg(long = "count-lines", requires = "line_modes", conflicts_with = "size")]
+    count_lines: bool,
     /// Skip read errors in input
     #[arg(short = 'E')]
     skip_input_errors: bool,
@@ -128,8 +294,8 @@ struct PipeViewConfig {
     skip_output_errors: bool,
     /// Input filenames as positional arguments. Use -, /dev/stdin, or leave empty to use stdin
     input_filenames: Vec<String>,
-    /// Show message every N seconds instead of once per block (useful for high throughput streams)
-    #[arg(short = 'i')]
+    /// Update the display every N seconds instead of on every block, even while no data is flowing. Numeric output defaults to every second
+    #[arg(short = 'i', value_parser = parse_interval)]
     interval: Option<f64>,
     /// Prefix the bar with this message
     #[arg(short = 'N')]
@@ -152,18 +318,117 @@ struct PipeViewConfig {
     /// Custom format string
     #[arg(short = 'F', long = "format")]
     format: Option<String>,
-    /// Numeric output - write integer values to stderr instead of visual progress
+    /// Numeric output - write integer values to stderr instead of visual progress, as GNU pv does
     #[arg(short = 'n', long = "numeric")]
     numeric: bool,
-    /// Rate limit data transfer to RATE bytes per second (k/m/g/t suffixes allowed)
+    /// Numeric output with human-readable units instead of raw numbers (implies -n)
+    #[arg(long = "numeric-human")]
+    numeric_human: bool,
+    /// Write progress as newline-delimited JSON in place of the bar, or to file descriptor FD with --json=FD
+    #[arg(
+        long = "json",
+        value_name = "FD",
+        num_args = 0..=1,
+        require_equals = true,
+        value_parser = parse_progress_fd
+    )]
+    json: Option<Option<i32>>,
+    /// Write progress (bar, numeric or JSON) to file descriptor FD instead of stderr
+    #[arg(
+        long = "progress-fd",
+        value_name = "FD",
+        value_parser = parse_progress_fd,
+        conflicts_with = "progress_file"
+    )]
+    progress_fd: Option<i32>,
+    /// Keep the latest progress update in PATH, replacing it atomically on every update
+    #[arg(long = "progress-file", value_name = "PATH")]
+    progress_file: Option<String>,
+    /// Rate limit data transfer to RATE per second, like 500k, 12.5M, 100Mbit or 2MiB. Plain numbers and k/m/g/t count in the display's units, so bits with -8 and 1000s with -k, while kB and Mbit are SI and KiB is binary
     #[arg(short = 'L', long = "rate-limit", value_parser = parse_rate_limit)]
-    rate_limit: Option<u64>,
+    rate_limit: Option<Amount>,
+    /// Change the rate limit with the local time, by rules like "Mon-Fri 08:00-18:00 20M; * unlimited" or @FILE for a file of them. The first matching rule applies, and -L applies when none does
+    #[arg(long = "rate-schedule", value_name = "RULES", value_parser = parse_rate_schedule)]
+    rate_schedule: Option<Schedule<Amount>>,
+    /// Raise the rate limit from zero to its full value over SECONDS, starting when the first data arrives
+    #[arg(long = "ramp", value_name = "SECONDS", value_parser = parse_interval, requires = "limits")]
+    ramp: Option<f64>,
+    /// How --ramp raises the rate limit
+    #[arg(
+        long = "ramp-shape",
+        value_enum,
+        default_value = "linear",
+        requires = "ramp"
+    )]
+    ramp_shape: RampShape,
+    /// Lower the rate limit while the system is under I/O pressure, as Linux reports in /proc/pressure/io, and raise it again as the pressure eases
+    #[arg(long = "adaptive", requires = "limits")]
+    adaptive: bool,
+    /// Read the pressure for --adaptive from PATH instead, as a number or in the format of /proc/pressure
+    #[arg(long = "pressure-file", value_name = "PATH", requires = "adaptive")]
+    pressure_file: Option<String>,
+    /// Read the pressure for --adaptive from what a shell command prints
+    #[arg(
+        long = "pressure-command",
+        value_name = "COMMAND",
+        requires = "adaptive",
+        conflicts_with = "pressure_file"
+    )]
+    pressure_command: Option<String>,
+    /// Back off while the pressure is above PERCENT
+    #[arg(
+        long = "pressure-threshold",
+        value_name = "PERCENT",
+        default_value_t = 10.0,
+        requires = "adaptive"
+    )]
+    pressure_threshold: f64,
+    /// Share the -L rate limit with other pv processes on this host given the same NAME, so together they send no faster than it. Every member has to give the same -L, and count bytes or lines alike. The group keeps to the plain -L, so it can't go with --rate-schedule, --ramp or --adaptive
+    #[arg(
+        long = "rate-group",
+        value_name = "NAME",
+        requires = "rate_limit",
+        conflicts_with_all = ["rate_schedule", "ramp", "adaptive"]
+    )]
+    rate_group: Option<String>,
+    /// Let up to SIZE go at once under -L (default: a tenth of a second's worth)
+    #[arg(long = "burst", value_name = "SIZE", value_parser = parse_rate_limit, requires = "limits")]
+    burst: Option<Amount>,
+    /// Serve Prometheus metrics at http://ADDR/metrics, e.g. 127.0.0.1:9100
+    #[arg(long = "metrics-listen", value_name = "ADDR")]
+    metrics_listen: Option<String>,
+    /// Keep Prometheus metrics in PATH for the node exporter's textfile collector
+    #[arg(long = "metrics-textfile", value_name = "PATH")]
+    metrics_textfile: Option<String>,
+    /// Send StatsD metrics over UDP to HOST:PORT on every update
+    #[arg(long = "statsd", value_name = "HOST:PORT")]
+    statsd: Option<String>,
+    /// Prefix for StatsD metric names (default: pv, or pv.NAME with -N)
+    #[arg(long = "statsd-prefix", value_name = "PREFIX", requires = "statsd")]
+    statsd_prefix: Option<String>,
+    /// Record the timing of every chunk to FILE, for analysis afterwards
+    #[arg(long = "trace", value_name = "FILE")]
+    trace: Option<String>,
+    /// Format of the --trace file
+    #[arg(
+        long = "trace-format",
+        value_enum,
+        default_value = "csv",
+        requires = "trace"
+    )]
+    trace_format: TraceFormat,
+    /// Reproduce the chunk sizes and timing of a transfer recorded with --trace
+    #[arg(long = "replay-timing", value_name = "FILE")]
+    replay_timing: Option<String>,
     /// Output to file instead of stdout
     #[arg(short = 'o', long = "output")]
     output_file: Option<String>,
     /// Force output (show progress even if not connected to terminal)
     #[arg(short = 'f', long = "force")]
     force_output: bool,
+    /// Print a timestamped status line every -i seconds (default 10) instead of redrawing the bar. This is automatic when -f or --progress-fd send the bar somewhere that isn't a terminal
+    #[arg(long = "log-lines")]
+    log_lines: bool,
     /// Use SI units (1000-based) instead of binary units (1024-based)
     #[arg(short = 'k')]
     si_units: bool,
@@ -176,31 +441,124 @@ struct PipeViewConfig {
     /// Wait until first byte is read before showing any output
     #[arg(short = 'W', long = "wait")]
     wait_for_first_byte: bool,
+    /// Show a STALLED indicator once no data has moved for this many seconds (default: 10)
+    #[arg(long = "stall-after", value_name = "SECONDS", value_parser = parse_interval)]
+    stall_after: Option<f64>,
+    /// Give up with exit status 124 once no data has moved for this many seconds
+    #[arg(long = "stall-timeout", value_name = "SECONDS", value_parser = parse_interval)]
+    stall_timeout: Option<f64>,
+    /// When the transfer finishes, show how much of the time went on waiting for input, output and the rate limit
+    #[arg(long = "wait-summary")]
+    wait_summary: bool,
     /// Wait for SECONDS before showing output
-    #[arg(short = 'D', long = "delay")]
+    #[arg(short = 'D', long = "delay", value_name = "SECONDS", value_parser = parse_delay)]
     delay_start: Option<f64>,
 }
 
+impl PipeViewConfig {
+    /// The file descriptor progress goes to, unless it goes to a status file
+    fn progress_fd(&self) -> Option<i32> {
+        match self.progress_file {
+            Some(_) => None,
+            None => Some(self.progress_fd.unwrap_or(2)),
+        }
+    }
+
+    /// Whether --json records go wherever the rest of the progress does
+    fn json_follows_progress(&self) -> bool {
+        matches!(self.json, Some(fd) if fd.is_none() || fd == self.progress_fd())
+    }
+
+    /// Whether the progress output carries machine-readable output in place of the bar
+    fn machine_output(&self) -> bool {
+        self.numeric || self.json_follows_progress()
+    }
+
+    /// How long without data before the transfer counts as stalled
+    fn stall_after(&self) -> Duration {
+        Duration::from_secs_f64(self.stall_after.unwrap_or(10.0))
+    }
+
+    /// An amount from the command line in bytes, or lines with -l
+    fn resolve(&self, amount: Amount) -> f64 {
+        amount
+            .resolve(self.si_units, self.bits_mode, self.line_mode)
+            .unwrap_or_else(|e| {
+                PipeViewConfig::command()
+                    .error(clap::error::ErrorKind::ValueValidation, e)
+                    .exit()
+            })
+    }
+
+    /// Whether the rate limit changes by itself, so the bar should show it
+    fn limit_varies(&self) -> bool {
+        self.ramp.is_some() || self.adaptive
+    }
+
+    /// Where --adaptive reads the pressure from
+    fn pressure_source(&self) -> PressureSource {
+        match (&self.pressure_file, &self.pressure_command) {
+            (_, Some(command)) => PressureSource::Command(command.clone()),
+            (Some(path), _) => PressureSource::File(path.clone()),
+            _ => PressureSource::File(PSI_IO.to_string()),
+        }
+    }
+
+    /// Whether Prometheus metrics are kept at all
+    fn metrics_enabled(&self) -> bool {
+        self.metrics_listen.is_some() || self.metrics_textfile.is_some()
+    }
+
+    /// Whether the bar is written as log lines rather than redrawn in place
+    fn writes_log_lines(&self) -> bool {
+        if self.quiet || self.machine_output() {
+            return false;
+        }
+        match self.progress_fd() {
+            Some(fd) => {
+                self.log_lines
+                    || ((self.force_output || self.progress_fd.is_some()) && !is_terminal(fd))
+            }
+            // A status file only holds one line anyway
+            None => false,
+        }
+    }
+
+    /// Open wherever progress goes
+    fn progress_output(&self) -> ProgressOutput {
+        if let Some(ref path) = self.progress_file {
+            return ProgressOutput::status_file(path)
+                .unwrap_or_else(|e| panic!("Failed to create progress file '{}': {}", path, e));
+        }
+        match self.progress_fd {
+            Some(fd) => open_progress_fd(fd),
+            None => ProgressOutput::stderr(),
+        }
+    }
+}
+
 fn main() {
     let mut matches = PipeViewConfig::parse();
+    let size_given = matches.size.is_some();
+    if matches.numeric_human {
+        matches.numeric = true;
+    }
+
+    // Progress on stdout would be mixed in with the data, unless that goes to -o
+    let stdout_progress = matches.progress_fd == Some(1) || matches.json == Some(Some(1));
+    if stdout_progress && matches.output_file.is_none() {
+        PipeViewConfig::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "File descriptor 1 carries the data, so progress can only go there with -o",
+            )
+            .exit();
+    }
 
     // Guess an expected size if possible
-    matches.size = Some(
-        matches.size.unwrap_or(
-            matches
-                .input_filenames
-                .iter()
-                .filter(|fname| fname.as_str() != "-") // Skip stdin
-                .map(|fname| {
-                    File::open(fname)
-                        .expect("Failed to open file")
-                        .metadata()
-                        .expect("Could not stat file")
-                        .len()
-                })
-                .sum(),
-        ),
-    );
+    if matches.size.is_none() {
+        matches.size = guess_input_size(&matches.input_filenames);
+    }
 
     let sources = if matches.input_filenames.is_empty() {
         Box::new(io::stdin()) as Box<dyn Read>
@@ -232,41 +590,351 @@ fn main() {
         Box::new(io::BufWriter::new(io::stdout()))
     };
 
-    PipeView {
-        source: sources, // Source
-        sink,            // Sink
-        progress: PipeView::progress_from_options(&matches),
-        line_mode: if matches.line_mode {
-            LineMode::Line(if matches.null { 0 } else { 10 }) // default to unix newline
-        } else {
-            LineMode::Byte
-        },
-        skip_input_errors: matches.skip_input_errors,
-        skip_output_errors: matches.skip_output_errors,
+    if matches.delimiter.is_some() || matches.records.is_some() {
+        matches.line_mode = true;
+    }
+    let line_counter = if let Some(format) = matches.records {
+        LineMode::Record(RecordCounter::new(format))
+    } else {
+        LineMode::Line(match matches.delimiter.clone() {
+            Some(delimiter) => delimiter,
+            None if matches.null => Delimiter::new(vec![0]),
+            None => Delimiter::new(vec![b'\n']), // default to unix newline
+        })
+    };
+    // Byte mode only pays for counting lines if something reports them
+    let (line_mode, byte_mode_lines) = if matches.line_mode {
+        (line_counter, None)
+    } else if matches.json.is_some()
+        || matches.metrics_enabled()
+        || matches.statsd.is_some()
+        || format_shows_lines(&matches)
+    {
+        (LineMode::Byte, Some(line_counter))
+    } else {
+        (LineMode::Byte, None)
+    };
+
+    // In line mode, -s is a line count but a guessed size is in bytes,
+    // so the line total has to be counted or estimated instead
+    let expected_bytes = match line_mode {
+        LineMode::Byte => None,
+        _ if size_given => None,
+        _ => matches.size,
+    };
+    let line_total = Arc::new(OnceLock::new());
+    if expected_bytes.is_some() && matches.count_lines {
+        spawn_line_count(
+            &matches.input_filenames,
+            line_mode.clone(),
+            line_total.clone(),
+        );
+    }
+
+    let counters = Arc::new(Counters::default());
+    let progress_output = Arc::new(Mutex::new(matches.progress_output()));
+    let json_output = match matches.json {
+        _ if matches.json_follows_progress() => Some(progress_output.clone()),
+        Some(Some(fd)) => Some(Arc::new(Mutex::new(open_progress_fd(fd)))),
+        _ => None,
+    };
+    let bar_target = BarTarget {
+        output: (matches.progress_file.is_some() || matches.progress_fd.is_some())
+            .then(|| progress_output.clone()),
+    };
+    let progress = PipeView::progress_from_options(&matches, &counters, &bar_target);
+    let display_gate = DisplayGate::new(&progress, &matches, bar_target);
+    let metrics = matches.metrics_enabled().then(|| {
+        let path = matches.metrics_textfile.as_deref();
+        Metrics::new(matches.name.clone(), matches.line_mode, path).unwrap_or_else(|e| {
+            panic!(
+                "Failed to create metrics file '{}': {}",
+                path.unwrap_or(""),
+                e
+            )
+        })
+    });
+    if let (Some(metrics), Some(addr)) = (&metrics, &matches.metrics_listen) {
+        metrics
+            .serve(addr)
+            .unwrap_or_else(|e| panic!("Failed to listen on '{}': {}", addr, e));
+    }
+    let statsd = matches.statsd.as_ref().map(|addr| {
+        StatsD::connect(
+            addr,
+            matches.statsd_prefix.as_deref(),
+            matches.name.as_deref(),
+        )
+        .unwrap_or_else(|e| panic!("Failed to connect to StatsD at '{}': {}", addr, e))
+    });
+    let log_lines = matches.writes_log_lines();
+    let notifier = Notifier::from_env();
+    let line_renderer =
+        (log_lines || notifier.is_some()).then(|| LineRenderer::new(&matches, &counters));
+    let reporter = Arc::new(Reporter {
+        progress: progress.clone(),
+        counters: counters.clone(),
+        display_gate,
+        timed_bar: matches.interval.is_some(),
+        counts_lines: matches.line_mode,
         numeric_mode: matches.numeric,
         quiet_mode: matches.quiet,
         numeric_config: NumericConfig {
+            human: matches.numeric_human,
             show_timer: matches.timer,
             show_bytes: matches.bytes,
             show_rate: matches.rate || matches.average_rate,
+            average_rate: matches.average_rate && !matches.rate,
             format_string: matches.format.clone(),
         },
         si_units: matches.si_units,
         bits_mode: matches.bits_mode,
-        last_numeric_output: std::time::Instant::now(),
-        numeric_output_count: 0,
-        rate_limit: matches.rate_limit,
-        rate_limit_start: std::time::Instant::now(),
-        total_bytes_transferred: 0,
+        name: matches.name.clone(),
+        stall_after: matches.stall_after(),
+        wait_summary: matches.wait_summary,
+        progress_output,
+        json_output,
+        log_lines,
+        line_renderer,
+        metrics,
+        statsd,
+        notifier,
+        last_sample: Mutex::new((0.0, 0)),
+        last_numeric: Mutex::new((0.0, 0)),
+        finished: Mutex::new(false),
+    });
+    // Numeric output follows GNU pv in updating once a second by default
+    let update_interval = match matches.interval {
+        Some(seconds) => Some(seconds),
+        None if matches.numeric || matches.json.is_some() => Some(1.0),
+        None if reporter.metrics.is_some() || reporter.statsd.is_some() => Some(1.0),
+        None if log_lines => Some(10.0),
+        None if reporter.notifier.is_some() => Some(1.0),
+        // Keep redrawing the bar while no data arrives, so a stall shows up
+        None if reporter.display_gate.reveal_bar => Some(1.0),
+        None => None,
+    };
+    if let Some(seconds) = matches.stall_timeout {
+        reporter.spawn_stall_timeout(Duration::from_secs_f64(seconds));
+    }
+    // The systemd watchdog wants pinging at least twice in each of its periods
+    let watchdog_interval = reporter
+        .notifier
+        .as_ref()
+        .and(Notifier::watchdog_interval())
+        .map(|period| period.as_secs_f64() / 2.0);
+    if let Some(seconds) = update_interval {
+        let seconds = watchdog_interval.map_or(seconds, |watchdog| seconds.min(watchdog));
+        reporter.spawn_ticker(Duration::from_secs_f64(seconds));
+    }
+    if let Some(ref notifier) = reporter.notifier {
+        notifier.notify("READY=1");
+    }
+
+    let trace = matches.trace.as_ref().map(|path| {
+        Tracer::create(path, matches.trace_format, Instant::now())
+            .unwrap_or_else(|e| panic!("Failed to create trace file '{}': {}", path, e))
+    });
+
+    let replay = matches.replay_timing.as_ref().map(|path| {
+        Replay::load(path)
+            .unwrap_or_else(|e| panic!("Failed to read replay file '{}': {}", path, e))
+    });
+
+    let rate_limit = matches.rate_limit.map(|rate| matches.resolve(rate));
+    let limiter = (rate_limit.is_some_and(|rate| rate > 0.0) || matches.rate_schedule.is_some())
+        .then(|| {
+            Limiter::new(
+                rate_limit,
+                matches.burst.map(|burst| matches.resolve(burst)),
+                matches
+                    .rate_schedule
+                    .clone()
+                    .map(|schedule| schedule.map(|rate| matches.resolve(rate))),
+                matches
+                    .rate_group
+                    .as_ref()
+                    .zip(rate_limit.filter(|&rate| rate > 0.0))
+                    .map(|(name, limit)| {
+                        let unit = if matches.line_mode { "lines" } else { "bytes" };
+                        RateGroup::join(name, limit, unit).unwrap_or_else(|e| {
+                            panic!("Failed to join rate group '{}': {}", name, e)
+                        })
+                    }),
+                matches
+                    .ramp
+                    .map(|seconds| Ramp::new(Duration::from_secs_f64(seconds), matches.ramp_shape)),
+                matches.adaptive.then(|| {
+                    let source = matches.pressure_source();
+                    let name = source.to_string();
+                    let pressure =
+                        Pressure::watch(source, Duration::from_secs(1)).unwrap_or_else(|e| {
+                            panic!("Failed to read pressure from '{}': {}", name, e)
+                        });
+                    Adaptive::new(pressure, matches.pressure_threshold)
+                }),
+            )
+        });
+    counters.set_limit(limiter.as_ref().and_then(Limiter::rate));
+
+    PipeView {
+        source: sources, // Source
+        sink,            // Sink
+        progress,
+        counters,
+        reporter,
+        line_mode,
+        byte_mode_lines,
+        expected_bytes,
+        line_total,
+        skip_input_errors: matches.skip_input_errors,
+        skip_output_errors: matches.skip_output_errors,
+        limiter,
+        replay_start: Instant::now(),
+        trace,
+        replay,
         stop_at_size: matches.stop_at_size,
-        wait_for_first_byte: matches.wait_for_first_byte,
-        delay_start: matches.delay_start,
-        first_byte_received: false,
+        flush_each_chunk: matches.stall_timeout.is_some(),
     }
     .pipeview()
     .unwrap();
 }
 
+/// Add up the sizes of all the inputs, or None if any of them has no size (like a pipe)
+fn guess_input_size(filenames: &[String]) -> Option<u64> {
+    let stdin_only = ["-".to_string()];
+    let filenames = if filenames.is_empty() {
+        &stdin_only[..]
+    } else {
+        filenames
+    };
+
+    let mut stdin_counted = false;
+    filenames
+        .iter()
+        .map(|fname| match fname.as_str() {
+            // Stdin is drained by its first occurrence, so later ones add nothing
+            "-" if stdin_counted => Some(0),
+            "-" => {
+                stdin_counted = true;
+                remaining_size(&mut stdin_file()?)
+            }
+            _ => remaining_size(&mut File::open(fname).expect("Failed to open file")),
+        })
+        .sum()
+}
+
+/// How many bytes are left to read from a file, if that can be known up front.
+///
+/// Only regular files and block devices have a size. Seeking to the end finds
+/// it for both, and the original position is restored afterwards in case the
+/// file is shared, as it is with stdin.
+fn remaining_size(file: &mut File) -> Option<u64> {
+    let file_type = file.metadata().ok()?.file_type();
+    if !(file_type.is_file() || is_block_device(&file_type)) {
+        return None;
+    }
+    let current = file.stream_position().ok()?;
+    let end = file.seek(SeekFrom::End(0)).ok()?;
+    file.seek(SeekFrom::Start(current)).ok()?;
+    Some(end.saturating_sub(current))
+}
+
+#[cfg(unix)]
+fn is_block_device(file_type: &std::fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    file_type.is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_file_type: &std::fs::FileType) -> bool {
+    false
+}
+
+/// A second handle on stdin, so it can be inspected like any other file
+#[cfg(unix)]
+fn stdin_file() -> Option<File> {
+    use std::os::unix::io::AsFd;
+    io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .ok()
+        .map(File::from)
+}
+
+#[cfg(windows)]
+fn stdin_file() -> Option<File> {
+    use std::os::windows::io::AsHandle;
+    io::stdin()
+        .as_handle()
+        .try_clone_to_owned()
+        .ok()
+        .map(File::from)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stdin_file() -> Option<File> {
+    None
+}
+
+/// Count delimiters in the input files on a background thread.
+///
+/// Stdin can't be read twice, so nothing is counted if it is one of the inputs.
+fn spawn_line_count(filenames: &[String], mut line_mode: LineMode, line_total: Arc<OnceLock<u64>>) {
+    if filenames.is_empty() || filenames.iter().any(|fname| fname == "-") {
+        return;
+    }
+    let filenames = filenames.to_vec();
+    thread::spawn(move || {
+        let mut buf = [0; DEFAULT_BUF_SIZE];
+        let mut lines = 0;
+        for fname in filenames {
+            let Ok(mut file) = File::open(fname) else {
+                return;
+            };
+            loop {
+                match file.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => lines += line_mode.count(&buf[..len]),
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(_) => return,
+                }
+            }
+        }
+        let _ = line_total.set(lines + line_mode.finish());
+    });
+}
+
+/// Open a file descriptor given on the command line for progress output
+fn open_progress_fd(fd: i32) -> ProgressOutput {
+    ProgressOutput::fd(fd)
+        .unwrap_or_else(|e| panic!("Failed to open file descriptor {}: {}", fd, e))
+}
+
+/// Where the visual bar is drawn once it is shown
+struct BarTarget {
+    /// Set when progress has been sent somewhere other than stderr
+    output: Option<Arc<Mutex<ProgressOutput>>>,
+}
+
+impl BarTarget {
+    fn draw_target(&self) -> ProgressDrawTarget {
+        match self.output {
+            Some(ref output) => ProgressDrawTarget::term_like(Box::new(BarTerm(output.clone()))),
+            None => ProgressDrawTarget::stderr(),
+        }
+    }
+}
+
+/// Whether the custom format needs a line count
+fn format_shows_lines(conf: &PipeViewConfig) -> bool {
+    conf.format.as_deref().is_some_and(|format_str| {
+        parse_format_string(format_str)
+            .iter()
+            .any(|token| matches!(token, FormatToken::Lines | FormatToken::LineRate))
+    })
+}
+
 /// Prevent a bunch of boxing noise by forcing a cast
 
 #[derive(Debug, Clone)]
@@ -281,7 +949,14 @@ enum FormatToken {
     Rate,
     AverageRate,
     Bytes,
+    Lines,
+    LineRate,
     Name,
+    Stall,
+    ReadWait,
+    WriteWait,
+    ThrottleWait,
+    Limit,
 }
 
 fn parse_format_string(format_str: &str) -> Vec<FormatToken> {
@@ -340,7 +1015,14 @@ fn parse_format_string(format_str: &str) -> Vec<FormatToken> {
                     "rate" => FormatToken::Rate,
                     "average-rate" => FormatToken::AverageRate,
                     "bytes" | "transferred" => FormatToken::Bytes,
+                    "lines" => FormatToken::Lines,
+                    "line-rate" => FormatToken::LineRate,
                     "name" => FormatToken::Name,
+                    "stall" => FormatToken::Stall,
+                    "read-wait" => FormatToken::ReadWait,
+                    "write-wait" => FormatToken::WriteWait,
+                    "throttle-wait" => FormatToken::ThrottleWait,
+                    "limit" => FormatToken::Limit,
                     _ => FormatToken::Text(format!("%{{{format_name}}}")), // Unknown format
                 };
                 tokens.push(token);
@@ -403,6 +1085,8 @@ fn build_indicatif_template(tokens: &[FormatToken], conf: &PipeViewConfig) -> St
             FormatToken::Fineta => template.push_str("{eta_precise}"), // Same as eta for now
             FormatToken::Rate => template.push_str(per_sec_name),
             FormatToken::AverageRate => template.push_str(per_sec_name), // Same as rate for now
+            // Bytes are always bytes, even when the bar counts lines
+            FormatToken::Bytes if conf.line_mode => template.push_str("{transferred_bytes}"),
             FormatToken::Bytes => {
                 if conf.size.is_some() {
                     template.push_str(&format!("{pos_name}/{len_name}"));
@@ -410,6 +1094,13 @@ fn build_indicatif_template(tokens: &[FormatToken], conf: &PipeViewConfig) -> St
                     template.push_str(pos_name);
                 }
             }
+            FormatToken::Lines => template.push_str("{lines}"),
+            FormatToken::LineRate => template.push_str("{line_rate}"),
+            FormatToken::Stall => template.push_str("{stall}"),
+            FormatToken::ReadWait => template.push_str("{read_wait}"),
+            FormatToken::WriteWait => template.push_str("{write_wait}"),
+            FormatToken::ThrottleWait => template.push_str("{throttle_wait}"),
+            FormatToken::Limit => template.push_str("{limit}"),
             FormatToken::Name => {
                 if let Some(ref name) = conf.name {
                     template.push_str(name);
@@ -422,89 +1113,836 @@ fn build_indicatif_template(tokens: &[FormatToken], conf: &PipeViewConfig) -> St
     template
 }
 
+/// Running totals kept independently of what the progressbar counts
+#[derive(Default)]
+struct Counters {
+    bytes: AtomicU64,
+    lines: AtomicU64,
+    /// Errors skipped with -E
+    read_errors: AtomicU64,
+    /// Errors skipped with -O
+    write_errors: AtomicU64,
+    /// Time spent sleeping for the rate limit
+    throttle_nanos: AtomicU64,
+    /// Time spent waiting for the input
+    read_wait_nanos: AtomicU64,
+    /// Time spent waiting for the output
+    write_wait_nanos: AtomicU64,
+    /// When data was last read, in nanoseconds since the transfer started
+    last_read_nanos: AtomicU64,
+    /// When data was last written, in nanoseconds since the transfer started
+    last_write_nanos: AtomicU64,
+    /// The rate limit in force, as the bits of an f64, or 0 for none
+    limit: AtomicU64,
+}
+
+impl Counters {
+    /// Add the template keys that show these totals, whatever the bar counts
+    fn add_template_keys(
+        self: &Arc<Self>,
+        style: ProgressStyle,
+        conf: &PipeViewConfig,
+    ) -> ProgressStyle {
+        let stall_after = conf.stall_after();
+        let (si_units, bits_mode, counts_lines) = (conf.si_units, conf.bits_mode, conf.line_mode);
+        let bytes = self.clone();
+        let limit = self.clone();
+        let lines = self.clone();
+        let line_rate = self.clone();
+        let stall = self.clone();
+        let mut style = style
+            .with_key(
+                "transferred_bytes",
+                move |_: &_, w: &mut dyn std::fmt::Write| {
+                    let transferred = bytes.bytes.load(Ordering::Relaxed);
+                    // Follow -k and -8 like the byte-mode keys do
+                    let _ = if si_units || bits_mode {
+                        write!(w, "{}", format_units(transferred, si_units, bits_mode))
+                    } else {
+                        write!(w, "{}", HumanBytes(transferred))
+                    };
+                },
+            )
+            .with_key("lines", move |_: &_, w: &mut dyn std::fmt::Write| {
+                let _ = write!(w, "{}", HumanCount(lines.lines.load(Ordering::Relaxed)));
+            })
+            .with_key(
+                "line_rate",
+                move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    let _ = write!(w, "{}/s", HumanCount(line_rate.line_rate(state.elapsed())));
+                },
+            )
+            .with_key(
+                "stall",
+                move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    if let Some(idle) = stall.stall(state.elapsed(), stall_after) {
+                        let _ = write!(w, "STALLED {}", FormattedDuration(idle));
+                    }
+                },
+            )
+            .with_key("limit", move |_: &_, w: &mut dyn std::fmt::Write| {
+                let _ = match limit.limit() {
+                    None => write!(w, "unlimited"),
+                    Some(rate) if counts_lines => write!(w, "{}/s", HumanFloatCount(rate)),
+                    Some(rate) => write!(w, "{}/s", format_units(rate as u64, si_units, bits_mode)),
+                };
+            });
+        for (key, wait) in [
+            (
+                "read_wait",
+                Counters::read_wait as fn(&Counters) -> &AtomicU64,
+            ),
+            ("write_wait", Counters::write_wait),
+            ("throttle_wait", Counters::throttle_wait),
+        ] {
+            let counters = self.clone();
+            style = style.with_key(
+                key,
+                move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    let percent = Counters::wait_percent(wait(&counters), state.elapsed());
+                    let _ = write!(w, "{percent:.0}%");
+                },
+            );
+        }
+        style
+    }
+
+    fn set_limit(&self, rate: Option<f64>) {
+        self.limit
+            .store(rate.map_or(0, f64::to_bits), Ordering::Relaxed);
+    }
+
+    /// The rate limit in force, in units a second
+    fn limit(&self) -> Option<f64> {
+        Some(self.limit.load(Ordering::Relaxed))
+            .filter(|&bits| bits != 0)
+            .map(f64::from_bits)
+    }
+
+    fn read_wait(&self) -> &AtomicU64 {
+        &self.read_wait_nanos
+    }
+
+    fn write_wait(&self) -> &AtomicU64 {
+        &self.write_wait_nanos
+    }
+
+    fn throttle_wait(&self) -> &AtomicU64 {
+        &self.throttle_nanos
+    }
+
+    /// Add to the time spent on something
+    fn add_time(total: &AtomicU64, time: Duration) {
+        total.fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// The share of the elapsed time spent waiting on something
+    fn wait_percent(wait: &AtomicU64, elapsed: Duration) -> f64 {
+        let wait = Duration::from_nanos(wait.load(Ordering::Relaxed));
+        if elapsed.is_zero() {
+            0.0
+        } else {
+            (wait.as_secs_f64() * 100.0 / elapsed.as_secs_f64()).min(100.0)
+        }
+    }
+
+    /// Record that data moved `elapsed` into the transfer
+    fn mark_activity(activity: &AtomicU64, elapsed: Duration) {
+        activity.fetch_max(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// How long it has been since data was last read or written
+    fn idle(&self, elapsed: Duration) -> Duration {
+        let last_read = self.last_read_nanos.load(Ordering::Relaxed);
+        let last_write = self.last_write_nanos.load(Ordering::Relaxed);
+        elapsed.saturating_sub(Duration::from_nanos(last_read.max(last_write)))
+    }
+
+    /// The idle time, once it has gone on long enough to count as a stall
+    fn stall(&self, elapsed: Duration, stall_after: Duration) -> Option<Duration> {
+        Some(self.idle(elapsed)).filter(|idle| *idle >= stall_after)
+    }
+
+    /// Average lines per second over the elapsed time
+    fn line_rate(&self, elapsed: Duration) -> u64 {
+        let elapsed = elapsed.as_secs_f64();
+        if elapsed > 0.0 {
+            (self.lines.load(Ordering::Relaxed) as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Clone)]
 enum LineMode {
-    Line(u8),
+    Line(Delimiter),
+    Record(RecordCounter),
     Byte,
 }
+
+impl LineMode {
+    /// Count the units (lines, records or bytes) in a chunk of the stream
+    fn count(&mut self, buf: &[u8]) -> u64 {
+        match self {
+            LineMode::Line(delim) => delim.count(buf),
+            LineMode::Record(counter) => counter.count(buf),
+            LineMode::Byte => buf.len() as u64,
+        }
+    }
+
+    /// Consume `buf` up to the end of the first line or record in it, returning where that is
+    fn next_end(&mut self, buf: &[u8]) -> Option<usize> {
+        match self {
+            LineMode::Line(delim) => delim.next_end(buf),
+            LineMode::Record(counter) => counter.next_end(buf),
+            LineMode::Byte => None,
+        }
+    }
+
+    /// Count any unit left unterminated at the end of the stream
+    fn finish(&mut self) -> u64 {
+        match self {
+            LineMode::Record(counter) => counter.finish(),
+            _ => 0,
+        }
+    }
+}
 struct PipeView {
     source: Box<dyn Read>,
     sink: Box<dyn Write>,
     progress: ProgressBar,
+    counters: Arc<Counters>,
+    reporter: Arc<Reporter>,
     line_mode: LineMode,
+    /// Counts lines for the format tokens while the bar itself counts bytes
+    byte_mode_lines: Option<LineMode>,
+    /// Byte size of the input when the line total has to be estimated from it
+    expected_bytes: Option<u64>,
+    /// Exact line total, once the background line count finishes
+    line_total: Arc<OnceLock<u64>>,
     skip_input_errors: bool,
     skip_output_errors: bool,
-    numeric_mode: bool,
-    quiet_mode: bool,
-    numeric_config: NumericConfig,
-    si_units: bool,
-    bits_mode: bool,
-    last_numeric_output: std::time::Instant,
-    numeric_output_count: u64,
-    rate_limit: Option<u64>,
-    rate_limit_start: std::time::Instant,
-    total_bytes_transferred: u64,
+    /// Set with -L, unless it is 0
+    limiter: Option<Limiter>,
+    /// What replayed chunk times are relative to
+    replay_start: Instant,
+    /// Set with --trace
+    trace: Option<Tracer>,
+    /// Set with --replay-timing
+    replay: Option<Replay>,
     stop_at_size: Option<u64>,
-    wait_for_first_byte: bool,
-    delay_start: Option<f64>,
-    first_byte_received: bool,
+    /// Set with --stall-timeout, whose exit can't flush, so nothing read waits in the buffer
+    flush_each_chunk: bool,
 }
 
 #[derive(Debug, Clone)]
 struct NumericConfig {
+    /// Human-readable units instead of GNU pv's raw numbers
+    human: bool,
     show_timer: bool,
     show_bytes: bool,
     show_rate: bool,
+    /// The rate shown is the average since the start, rather than since the last update
+    average_rate: bool,
     format_string: Option<String>,
 }
 
+/// Keeps the display hidden until the -D delay has passed and, with -W, data has arrived.
+///
+/// Only the display waits; data is copied at full speed from the start.
+struct DisplayGate {
+    progress: ProgressBar,
+    /// Whether opening the gate should reveal the visual bar (not for -q or -n)
+    reveal_bar: bool,
+    bar_target: BarTarget,
+    wait_for_first_byte: bool,
+    first_byte_received: AtomicBool,
+    start: Instant,
+    delay: Duration,
+    open: AtomicBool,
+}
+
+impl DisplayGate {
+    fn new(
+        progress: &ProgressBar,
+        conf: &PipeViewConfig,
+        bar_target: BarTarget,
+    ) -> Arc<DisplayGate> {
+        let delay = Duration::from_secs_f64(conf.delay_start.unwrap_or(0.0));
+        let gate = Arc::new(DisplayGate {
+            progress: progress.clone(),
+            reveal_bar: !(conf.quiet || conf.machine_output()),
+            bar_target,
+            wait_for_first_byte: conf.wait_for_first_byte,
+            first_byte_received: AtomicBool::new(false),
+            start: Instant::now(),
+            delay,
+            open: AtomicBool::new(false),
+        });
+
+        if gate.wait_for_first_byte || !delay.is_zero() {
+            if gate.reveal_bar {
+                progress.set_draw_target(ProgressDrawTarget::hidden());
+            }
+            // Open on time even if no data is flowing
+            let timer = gate.clone();
+            thread::spawn(move || {
+                thread::sleep(delay);
+                timer.try_open();
+            });
+        } else {
+            gate.open.store(true, Ordering::SeqCst);
+        }
+        gate
+    }
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    fn first_byte(&self) {
+        if !self.first_byte_received.swap(true, Ordering::SeqCst) {
+            self.try_open();
+        }
+    }
+
+    /// Show the display if every condition for it has been met
+    fn try_open(&self) {
+        let ready = self.start.elapsed() >= self.delay
+            && (!self.wait_for_first_byte || self.first_byte_received.load(Ordering::SeqCst));
+        if ready && !self.open.swap(true, Ordering::SeqCst) && self.reveal_bar {
+            self.progress.set_draw_target(self.bar_target.draw_target());
+        }
+    }
+}
+
+/// Renders the bar as a line of text, for outputs that take a line at a time.
+///
+/// This is a second bar in the same style, so it works whether or not the
+/// real one is drawn anywhere.
+struct LineRenderer {
+    bar: ProgressBar,
+    frame: Arc<Mutex<String>>,
+}
+
+impl LineRenderer {
+    fn new(conf: &PipeViewConfig, counters: &Arc<Counters>) -> LineRenderer {
+        let frame = Arc::new(Mutex::new(String::new()));
+        let target = ProgressDrawTarget::term_like(Box::new(LogTerm::new(frame.clone())));
+        let bar = ProgressBar::with_draw_target(conf.size, target);
+        bar.set_style(counters.add_template_keys(PipeView::bar_style(conf), conf));
+        LineRenderer { bar, frame }
+    }
+
+    /// Render the bar at `position` out of `length`
+    fn render(&self, position: u64, length: Option<u64>) -> String {
+        if let Some(length) = length {
+            self.bar.set_length(length);
+        }
+        self.bar.set_position(position);
+        self.bar.force_draw();
+        self.frame.lock().unwrap().clone()
+    }
+}
+
+/// Reports progress outside of the writes themselves: on a timer, and once at the end
+struct Reporter {
+    progress: ProgressBar,
+    counters: Arc<Counters>,
+    display_gate: Arc<DisplayGate>,
+    /// With -i, the bar only moves on each update instead of on every write
+    timed_bar: bool,
+    /// Whether the bar counts lines rather than bytes
+    counts_lines: bool,
+    numeric_mode: bool,
+    quiet_mode: bool,
+    numeric_config: NumericConfig,
+    si_units: bool,
+    bits_mode: bool,
+    name: Option<String>,
+    /// How long without data before the transfer counts as stalled
+    stall_after: Duration,
+    /// Show where the time went at the end
+    wait_summary: bool,
+    /// Where the visual bar and numeric output go
+    progress_output: Arc<Mutex<ProgressOutput>>,
+    /// Where --json records go, which may be the same place
+    json_output: Option<Arc<Mutex<ProgressOutput>>>,
+    /// Write the bar as timestamped log lines instead of drawing it
+    log_lines: bool,
+    /// Renders the bar for log lines and systemd status
+    line_renderer: Option<LineRenderer>,
+    metrics: Option<Arc<Metrics>>,
+    statsd: Option<StatsD>,
+    /// Set when running as a systemd service that wants notifications
+    notifier: Option<Notifier>,
+    /// Elapsed seconds and bytes at the previous snapshot, for the current rate
+    last_sample: Mutex<(f64, u64)>,
+    /// Elapsed seconds and position at the previous numeric line, for its current rate
+    last_numeric: Mutex<(f64, u64)>,
+    /// Set once the final update is out, so a late timer tick can't follow it
+    finished: Mutex<bool>,
+}
+
+impl Reporter {
+    /// Start a thread that updates every `interval`, whether or not data is flowing
+    fn spawn_ticker(self: &Arc<Self>, interval: Duration) {
+        let reporter = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            if !reporter.update() {
+                break;
+            }
+        });
+    }
+
+    /// Start a thread that exits once no data has moved for `timeout`
+    fn spawn_stall_timeout(self: &Arc<Self>, timeout: Duration) {
+        let reporter = self.clone();
+        thread::spawn(move || loop {
+            let idle = reporter.counters.idle(reporter.progress.elapsed());
+            if idle >= timeout {
+                // Leave the bar showing where the transfer got stuck
+                reporter.progress.abandon();
+                reporter.finish();
+                eprintln!(
+                    "pv: no data transferred for {}, giving up",
+                    FormattedDuration(idle)
+                );
+                std::process::exit(STALL_EXIT_STATUS);
+            }
+            thread::sleep((timeout - idle).min(Duration::from_millis(100)));
+        });
+    }
+
+    /// Units transferred so far, in whatever the bar counts
+    fn position(&self) -> u64 {
+        let counter = if self.counts_lines {
+            &self.counters.lines
+        } else {
+            &self.counters.bytes
+        };
+        counter.load(Ordering::Relaxed)
+    }
+
+    /// Show the latest totals, returning false once reporting has finished
+    fn update(&self) -> bool {
+        let finished = self.finished.lock().unwrap();
+        if *finished {
+            return false;
+        }
+        if self.timed_bar {
+            self.progress.set_position(self.position());
+        }
+        if self.timed_bar || self.display_gate.reveal_bar {
+            self.progress.tick();
+        }
+        self.output_numeric();
+        self.output_snapshot(false);
+        self.output_log_line();
+        self.output_notify();
+        true
+    }
+
+    /// Show the final totals
+    fn finish(&self) {
+        let mut finished = self.finished.lock().unwrap();
+        if *finished {
+            return;
+        }
+        *finished = true;
+        if self.timed_bar {
+            self.progress.set_position(self.position());
+        }
+        self.output_numeric();
+        self.output_snapshot(true);
+        self.output_log_line();
+        self.output_notify();
+        self.output_wait_summary();
+    }
+
+    /// Write a line saying how much of the transfer was spent waiting, and on what
+    fn output_wait_summary(&self) {
+        if !self.wait_summary || self.quiet_mode {
+            return;
+        }
+        // Put the bar in its final place first, so the summary goes below it
+        self.progress.finish_using_style();
+        let elapsed = self.progress.elapsed();
+        let wait = |total: &AtomicU64| {
+            format!(
+                "{:.3}s ({:.1}%)",
+                Duration::from_nanos(total.load(Ordering::Relaxed)).as_secs_f64(),
+                Counters::wait_percent(total, elapsed)
+            )
+        };
+        let summary = format!(
+            "Waited on input {}, output {}, rate limit {}",
+            wait(&self.counters.read_wait_nanos),
+            wait(&self.counters.write_wait_nanos),
+            wait(&self.counters.throttle_nanos)
+        );
+        let _ = self.progress_output.lock().unwrap().write_update(&summary);
+    }
+
+    /// Take a snapshot of the transfer, which starts a new period for the current rate
+    fn snapshot(&self) -> Snapshot {
+        let elapsed = self.progress.elapsed().as_secs_f64();
+        let bytes = self.counters.bytes.load(Ordering::Relaxed);
+        let (last_elapsed, last_bytes) =
+            std::mem::replace(&mut *self.last_sample.lock().unwrap(), (elapsed, bytes));
+        let per_second = |amount: u64, seconds: f64| {
+            if seconds > 0.0 {
+                amount as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+
+        let position = self.position();
+        let length = self.progress.length().filter(|length| *length > 0);
+        Snapshot {
+            elapsed,
+            bytes,
+            lines: self.counters.lines.load(Ordering::Relaxed),
+            rate: per_second(bytes.saturating_sub(last_bytes), elapsed - last_elapsed),
+            avg_rate: per_second(bytes, elapsed),
+            percent: length.map(|length| position as f64 * 100.0 / length as f64),
+            eta: length
+                .filter(|_| position > 0)
+                .map(|length| length.saturating_sub(position) as f64 * elapsed / position as f64),
+            read_errors: self.counters.read_errors.load(Ordering::Relaxed),
+            write_errors: self.counters.write_errors.load(Ordering::Relaxed),
+            throttle: Duration::from_nanos(self.counters.throttle_nanos.load(Ordering::Relaxed))
+                .as_secs_f64(),
+            size: length,
+        }
+    }
+
+    /// Send one snapshot to everything that takes them
+    fn output_snapshot(&self, done: bool) {
+        let snapshot = self.snapshot();
+        self.output_json(&snapshot, done);
+        if let Some(ref metrics) = self.metrics {
+            metrics.publish(&snapshot);
+        }
+        if let Some(ref statsd) = self.statsd {
+            statsd.publish(&snapshot);
+        }
+    }
+
+    /// Write a --json record
+    fn output_json(&self, snapshot: &Snapshot, done: bool) {
+        if self.quiet_mode || !self.display_gate.is_open() {
+            return;
+        }
+        if let Some(ref output) = self.json_output {
+            let record = snapshot.to_json(self.name.as_deref(), done);
+            // Progress is best-effort, so a closed reader mustn't stop the transfer
+            let _ = output.lock().unwrap().write_update(&record);
+        }
+    }
+
+    /// The bar as it stands now, as a line of text
+    fn render_line(&self) -> String {
+        self.line_renderer
+            .as_ref()
+            .map_or_else(String::new, |renderer| {
+                renderer.render(self.position(), self.progress.length())
+            })
+    }
+
+    /// Write the bar on a line of its own, with a timestamp
+    fn output_log_line(&self) {
+        if !self.log_lines || self.quiet_mode || !self.display_gate.is_open() {
+            return;
+        }
+        let line = format!("{} {}", log_timestamp(), self.render_line());
+        let _ = self.progress_output.lock().unwrap().write_update(&line);
+    }
+
+    /// Show the bar as the systemd service status, pinging the watchdog while data flows
+    fn output_notify(&self) {
+        if let Some(ref notifier) = self.notifier {
+            // Slow or bursty data is still alive, until it counts as stalled
+            let stall = self
+                .counters
+                .stall(self.progress.elapsed(), self.stall_after);
+            notifier.status(&self.render_line(), stall.is_some());
+        }
+    }
+
+    /// Elapsed seconds, with GNU pv's four decimal places unless output is human-readable
+    fn numeric_elapsed(&self) -> String {
+        let elapsed = self.progress.elapsed().as_secs_f64();
+        if self.numeric_config.human {
+            format!("{elapsed:.1}")
+        } else {
+            format!("{elapsed:.4}")
+        }
+    }
+
+    /// An amount of data: a raw count of bytes (or bits with -8), or human-readable units
+    fn numeric_bytes(&self, bytes: u64) -> String {
+        if self.numeric_config.human {
+            format_units(bytes, self.si_units, self.bits_mode)
+        } else if self.bits_mode {
+            (bytes * 8).to_string()
+        } else {
+            bytes.to_string()
+        }
+    }
+
+    /// Units transferred, counting lines as plain numbers
+    fn numeric_position(&self) -> String {
+        if self.counts_lines {
+            self.position().to_string()
+        } else {
+            self.numeric_bytes(self.position())
+        }
+    }
+
+    /// Units per second since the previous numeric line, and since the start
+    fn numeric_rates(&self) -> (f64, f64) {
+        let elapsed = self.progress.elapsed().as_secs_f64();
+        let position = self.position();
+        let (last_elapsed, last_position) =
+            std::mem::replace(&mut *self.last_numeric.lock().unwrap(), (elapsed, position));
+        let per_second = |amount: u64, seconds: f64| {
+            if seconds > 0.0 {
+                amount as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+        (
+            per_second(
+                position.saturating_sub(last_position),
+                elapsed - last_elapsed,
+            ),
+            per_second(position, elapsed),
+        )
+    }
+
+    /// A rate in units per second
+    fn numeric_rate(&self, rate: f64) -> String {
+        let elapsed = self.progress.elapsed().as_secs_f64();
+        if !self.numeric_config.human {
+            let scale = if self.bits_mode && !self.counts_lines {
+                8.0
+            } else {
+                1.0
+            };
+            format!("{:.4}", rate * scale)
+        } else if elapsed <= 0.0 {
+            "0".to_string()
+        } else if self.counts_lines {
+            format!("{}/s", rate as u64)
+        } else {
+            format!(
+                "{}/s",
+                format_units(rate as u64, self.si_units, self.bits_mode)
+            )
+        }
+    }
+
+    /// The rate limit in force, or 0 when unlimited
+    fn numeric_limit(&self) -> String {
+        let limit = self.counters.limit();
+        match limit {
+            None if self.numeric_config.human => "unlimited".to_string(),
+            Some(rate) if self.numeric_config.human && !self.counts_lines => {
+                format!(
+                    "{}/s",
+                    format_units(rate as u64, self.si_units, self.bits_mode)
+                )
+            }
+            Some(rate) if self.numeric_config.human => format!("{rate}/s"),
+            _ => {
+                let rate = limit.unwrap_or(0.0);
+                let scale = if self.bits_mode && !self.counts_lines {
+                    8.0
+                } else {
+                    1.0
+                };
+                format!("{:.4}", rate * scale)
+            }
+        }
+    }
+
+    /// Whole percentage of the time spent waiting on something
+    fn numeric_wait(&self, wait: &AtomicU64) -> String {
+        let percent = Counters::wait_percent(wait, self.progress.elapsed());
+        if self.numeric_config.human {
+            format!("{percent:.0}%")
+        } else {
+            format!("{percent:.0}")
+        }
+    }
+
+    /// Whole percentage complete, or the position when the size is unknown
+    fn numeric_percentage(&self) -> String {
+        if let Some(length) = self.progress.length() {
+            let percentage = (self.position() * 100).checked_div(length);
+            percentage.unwrap_or(0).to_string()
+        } else {
+            self.position().to_string()
+        }
+    }
+
+    /// Convert format tokens to numeric output values
+    fn format_token_to_numeric_value(
+        &self,
+        token: &FormatToken,
+        (rate, avg_rate): (f64, f64),
+    ) -> Option<String> {
+        match token {
+            FormatToken::Timer => Some(self.numeric_elapsed()),
+            FormatToken::Bytes => {
+                Some(self.numeric_bytes(self.counters.bytes.load(Ordering::Relaxed)))
+            }
+            FormatToken::Lines => Some(self.counters.lines.load(Ordering::Relaxed).to_string()),
+            FormatToken::LineRate => {
+                let rate = self.counters.line_rate(self.progress.elapsed());
+                if self.numeric_config.human {
+                    Some(format!("{rate}/s"))
+                } else {
+                    Some(rate.to_string())
+                }
+            }
+            FormatToken::Rate => Some(self.numeric_rate(rate)),
+            FormatToken::AverageRate => Some(self.numeric_rate(avg_rate)),
+            FormatToken::ProgressAmountOnly => Some(self.numeric_percentage()),
+            FormatToken::Text(text) => Some(text.clone()),
+            // For numeric mode, progress bars become percentage
+            FormatToken::Progress { .. } | FormatToken::ProgressBarOnly { .. } => {
+                Some(self.numeric_percentage())
+            }
+            FormatToken::Stall => {
+                let elapsed = self.progress.elapsed();
+                let stall = self.counters.stall(elapsed, self.stall_after);
+                Some(stall.map_or(0, |idle| idle.as_secs()).to_string())
+            }
+            FormatToken::ReadWait => Some(self.numeric_wait(&self.counters.read_wait_nanos)),
+            FormatToken::WriteWait => Some(self.numeric_wait(&self.counters.write_wait_nanos)),
+            FormatToken::ThrottleWait => Some(self.numeric_wait(&self.counters.throttle_nanos)),
+            FormatToken::Limit => Some(self.numeric_limit()),
+            // Ignore visual-only tokens in numeric mode
+            FormatToken::Eta | FormatToken::Fineta | FormatToken::Name => None,
+        }
+    }
+
+    /// Render one line of numeric output.
+    ///
+    /// By default this matches GNU pv: an optional elapsed time, then the
+    /// percentage (or the amount transferred with -b), then an optional rate,
+    /// all as bare numbers. Human-readable output keeps to the flags given.
+    fn numeric_line(&self) -> String {
+        let rates = self.numeric_rates();
+        if let Some(ref format_str) = self.numeric_config.format_string {
+            // Parse the format string and convert tokens to numeric values
+            return parse_format_string(format_str)
+                .iter()
+                .filter_map(|token| self.format_token_to_numeric_value(token, rates))
+                .collect();
+        }
+
+        let config = &self.numeric_config;
+        let mut parts = Vec::new();
+        if config.show_timer {
+            parts.push(self.numeric_elapsed());
+        }
+        if config.show_bytes {
+            parts.push(self.numeric_position());
+        } else if !config.human || !(config.show_timer || config.show_rate) {
+            parts.push(self.numeric_percentage());
+        }
+        if config.show_rate {
+            let (rate, avg_rate) = rates;
+            let rate = if config.average_rate { avg_rate } else { rate };
+            parts.push(self.numeric_rate(rate));
+        }
+        parts.join(" ")
+    }
+
+    /// Output numeric values based on configuration
+    fn output_numeric(&self) {
+        if !self.numeric_mode || self.quiet_mode || !self.display_gate.is_open() {
+            return;
+        }
+
+        let output = self.numeric_line();
+        if !output.is_empty() {
+            let _ = self.progress_output.lock().unwrap().write_update(&output);
+        }
+    }
+}
+
 impl PipeView {
     /// Create and configure a progress bar with the given style
     fn create_configured_progress_bar(
         size: Option<u64>,
         style: ProgressStyle,
         conf: &PipeViewConfig,
+        counters: &Arc<Counters>,
+        bar_target: &BarTarget,
     ) -> ProgressBar {
         let progress = match size {
             Some(x) => ProgressBar::new(x),
             None => ProgressBar::new_spinner(),
         };
 
-        progress.set_style(style);
-
-        // Optionally enable steady tick
-        if let Some(sec) = conf.interval {
-            progress.enable_steady_tick(Duration::from_secs_f64(sec));
-        }
+        progress.set_style(counters.add_template_keys(style, conf));
 
         // Force output to stderr even when not connected to terminal
-        if conf.force_output {
-            progress.set_draw_target(ProgressDrawTarget::stderr());
+        if conf.force_output || bar_target.output.is_some() {
+            progress.set_draw_target(bar_target.draw_target());
         }
 
         progress
     }
 
     /// Set up the progress bar from the parsed CLI options
-    fn progress_from_options(conf: &PipeViewConfig) -> ProgressBar {
-        // For quiet mode, create a completely hidden progress bar
-        if conf.quiet {
+    fn progress_from_options(
+        conf: &PipeViewConfig,
+        counters: &Arc<Counters>,
+        bar_target: &BarTarget,
+    ) -> ProgressBar {
+        // For quiet mode and log lines, create a completely hidden progress bar
+        if conf.quiet || conf.writes_log_lines() {
             let progress = Self::create_configured_progress_bar(
                 conf.size,
                 ProgressStyle::default_bar().template("").unwrap(),
                 conf,
+                counters,
+                bar_target,
             );
             progress.set_draw_target(ProgressDrawTarget::hidden());
             return progress;
         }
 
-        // For numeric mode, create a hidden progress bar
-        if conf.numeric {
+        // For numeric and JSON output, create a hidden progress bar
+        if conf.machine_output() {
             return Self::create_configured_progress_bar(
                 conf.size,
                 ProgressStyle::default_bar().template("").unwrap(),
                 conf,
+                counters,
+                bar_target,
             );
         }
+        Self::create_configured_progress_bar(
+            conf.size,
+            Self::bar_style(conf),
+            conf,
+            counters,
+            bar_target,
+        )
+    }
+
+    /// The style of the visual bar, from the format string or the individual flags
+    fn bar_style(conf: &PipeViewConfig) -> ProgressStyle {
         let mut style = match conf.size {
             Some(_x) => ProgressStyle::default_bar(),
             None => ProgressStyle::default_spinner(),
@@ -526,9 +1964,11 @@ impl PipeView {
                 template.push("{elapsed_precise}".to_string());
             }
 
-            match conf.width {
-                Some(x) => template.push(format!("{{bar:{x}}} {{percent}}")),
-                None => template.push("{wide_bar} {percent}%".to_string()),
+            // Without a size there's nothing to measure progress against
+            match (conf.size, conf.width) {
+                (None, _) => template.push("{spinner}".to_string()),
+                (Some(_), Some(x)) => template.push(format!("{{bar:{x}}} {{percent}}")),
+                (Some(_), None) => template.push("{wide_bar} {percent}%".to_string()),
             }
 
             // Choose whether you want bytes or plain counts on several fields
@@ -549,10 +1989,13 @@ impl PipeView {
                 template.push(per_sec_name.to_string());
             }
 
-            if conf.eta || conf.fineta {
+            if (conf.eta || conf.fineta) && conf.size.is_some() {
                 template.push("{eta_precise}".to_string());
             }
 
+            // Show where the limit has got to on its way up
+            let limit = if conf.limit_varies() { " {limit}" } else { "" };
+
             // Use default if no options specified
             if !(conf.timer
                 || conf.bytes
@@ -561,161 +2004,86 @@ impl PipeView {
                 || conf.eta
                 || conf.fineta)
             {
-                style = style.template(&format!(
-                    "{{elapsed}} {{wide_bar}} {{percent}}% {pos_name}/{len_name} {per_sec_name} {{eta}}"
-                )).unwrap();
+                let template = match conf.size {
+                    Some(_) => format!(
+                        "{{elapsed}} {{wide_bar}} {{percent}}% {pos_name}/{len_name} {per_sec_name}{limit} {{eta}} {{stall}}"
+                    ),
+                    None => format!(
+                        "{{spinner}} {{elapsed}} {pos_name} {per_sec_name}{limit} {{stall}}"
+                    ),
+                };
+                style = style.template(&template).unwrap();
             } else {
+                if conf.limit_varies() {
+                    template.push("{limit}".to_string());
+                }
+                template.push("{stall}".to_string());
                 style = style.template(&template.join(" ")).unwrap();
             }
         }
 
-        Self::create_configured_progress_bar(conf.size, style, conf)
+        add_unit_keys(style, conf)
     }
 
-    /// Convert format tokens to numeric output values
-    fn format_token_to_numeric_value(&self, token: &FormatToken) -> Option<String> {
-        match token {
-            FormatToken::Timer => Some(format!("{:.1}", self.progress.elapsed().as_secs_f64())),
-            FormatToken::Bytes => {
-                let bytes = self.progress.position();
-                Some(format_units(bytes, self.si_units, self.bits_mode))
-            }
-            FormatToken::Rate | FormatToken::AverageRate => {
-                let elapsed = self.progress.elapsed().as_secs_f64();
-                if elapsed > 0.0 {
-                    let rate = (self.progress.position() as f64 / elapsed) as u64;
-                    Some(format!(
-                        "{}/s",
-                        format_units(rate, self.si_units, self.bits_mode)
-                    ))
-                } else {
-                    Some("0".to_string())
-                }
-            }
-            FormatToken::ProgressAmountOnly => {
-                if let Some(length) = self.progress.length() {
-                    if length > 0 {
-                        let percentage = (self.progress.position() * 100) / length;
-                        Some(percentage.to_string())
-                    } else {
-                        Some("0".to_string())
-                    }
-                } else {
-                    // For unknown size, just show position
-                    Some(self.progress.position().to_string())
-                }
-            }
-            FormatToken::Text(text) => Some(text.clone()),
-            // For numeric mode, progress bars become percentage
-            FormatToken::Progress { .. } | FormatToken::ProgressBarOnly { .. } => {
-                if let Some(length) = self.progress.length() {
-                    if length > 0 {
-                        let percentage = (self.progress.position() * 100) / length;
-                        Some(percentage.to_string())
-                    } else {
-                        Some("0".to_string())
-                    }
-                } else {
-                    Some(self.progress.position().to_string())
-                }
-            }
-            // Ignore visual-only tokens in numeric mode
-            FormatToken::Eta | FormatToken::Fineta | FormatToken::Name => None,
+    /// Move the bar along, unless it only moves on timed updates
+    fn advance_bar(&self, units: u64) {
+        if !self.reporter.timed_bar {
+            self.progress.inc(units);
         }
     }
 
-    /// Output numeric values to stderr based on configuration
-    fn output_numeric(&self) {
-        if !self.numeric_mode || self.quiet_mode {
-            return;
+    /// Add to the line total, if lines are being counted at all
+    fn count_lines(&self, lines: u64) {
+        if !matches!(self.line_mode, LineMode::Byte) || self.byte_mode_lines.is_some() {
+            self.counters.lines.fetch_add(lines, Ordering::Relaxed);
         }
+    }
 
-        let output = if let Some(ref format_str) = self.numeric_config.format_string {
-            // Parse the format string and convert tokens to numeric values
-            let tokens = parse_format_string(format_str);
-            let mut parts = Vec::new();
-
-            for token in &tokens {
-                if let Some(value) = self.format_token_to_numeric_value(token) {
-                    parts.push(value);
-                }
-            }
-
-            parts.join("")
+    /// Size the progressbar in lines when only the byte size of the input is known.
+    ///
+    /// Uses the background line count if it has finished, and otherwise
+    /// extrapolates from the average bytes per line seen so far. Until the
+    /// first line arrives, the byte size is kept as an upper bound.
+    fn update_line_length(&self, bytes_so_far: u64) {
+        let Some(expected_bytes) = self.expected_bytes else {
+            return;
+        };
+        let lines = self.reporter.position();
+        let length = if let Some(&total) = self.line_total.get() {
+            total
+        } else if lines == 0 || bytes_so_far == 0 {
+            return;
         } else {
-            // Handle individual flags - use default numeric format
-            let mut parts = Vec::new();
-
-            if self.numeric_config.show_timer {
-                parts.push(format!("{:.1}", self.progress.elapsed().as_secs_f64()));
-            }
-
-            if self.numeric_config.show_bytes {
-                let bytes = self.progress.position();
-                parts.push(format_units(bytes, self.si_units, self.bits_mode));
-            }
-
-            if self.numeric_config.show_rate {
-                let elapsed = self.progress.elapsed().as_secs_f64();
-                if elapsed > 0.0 {
-                    let rate = (self.progress.position() as f64 / elapsed) as u64;
-                    parts.push(format!(
-                        "{}/s",
-                        format_units(rate, self.si_units, self.bits_mode)
-                    ));
-                } else {
-                    parts.push("0".to_string());
-                }
-            }
-
-            // Default: show percentage if size is known, otherwise position
-            if !self.numeric_config.show_timer
-                && !self.numeric_config.show_bytes
-                && !self.numeric_config.show_rate
-            {
-                if let Some(length) = self.progress.length() {
-                    if length > 0 {
-                        let percentage = (self.progress.position() * 100) / length;
-                        parts.push(percentage.to_string());
-                    } else {
-                        parts.push("0".to_string());
-                    }
-                } else {
-                    parts.push(self.progress.position().to_string());
-                }
-            }
-
-            parts.join(" ")
+            let estimate = lines as u128 * expected_bytes as u128 / bytes_so_far as u128;
+            (estimate as u64).max(lines)
         };
-
-        if !output.is_empty() {
-            eprintln!("{output}");
-        }
+        self.progress.set_length(length);
     }
 
-    /// Handle rate limiting by sleeping to maintain target rate
-    fn apply_rate_limit(&mut self, bytes_written: u64) {
-        if let Some(rate_limit) = self.rate_limit {
-            if rate_limit == 0 {
-                return; // No rate limiting if rate is 0
-            }
-
-            // Update total bytes transferred
-            self.total_bytes_transferred += bytes_written;
-
-            // Calculate how long we should have taken so far
-            let elapsed = self.rate_limit_start.elapsed();
-            let target_duration = std::time::Duration::from_secs_f64(
-                self.total_bytes_transferred as f64 / rate_limit as f64,
+    /// Sleep to pace the output, passing on written data first
+    fn pause(&mut self, duration: Duration) -> io::Result<()> {
+        if duration > Duration::from_millis(1) {
+            // Don't hold written data back in the buffer while we wait
+            self.flush_sink()?;
+            // Pacing the output isn't a stall
+            Counters::mark_activity(
+                &self.counters.last_write_nanos,
+                self.progress.elapsed() + duration,
             );
+            std::thread::sleep(duration);
+            Counters::add_time(&self.counters.throttle_nanos, duration);
+        }
+        Ok(())
+    }
 
-            // If we're ahead of schedule, sleep for the remaining time
-            if target_duration > elapsed {
-                let sleep_duration = target_duration - elapsed;
-                if sleep_duration > std::time::Duration::from_millis(1) {
-                    std::thread::sleep(sleep_duration);
-                }
-            }
+    /// Flush the output, maybe skipping errors
+    fn flush_sink(&mut self) -> io::Result<()> {
+        let flush_start = Instant::now();
+        let flushed = self.sink.flush();
+        Counters::add_time(&self.counters.write_wait_nanos, flush_start.elapsed());
+        match flushed {
+            Err(_) if self.skip_output_errors => Ok(()),
+            flushed => flushed,
         }
     }
 
@@ -726,32 +2094,40 @@ impl PipeView {
         loop {
             // Always skip interruptions, maybe skip other errors
             // Also maybe finish if we read nothing
-            let len = match self.source.read(&mut buf) {
+            let read_start = Instant::now();
+            let read = self.source.read(&mut buf);
+            let read_at = Instant::now();
+            let read_latency = read_at - read_start;
+            Counters::add_time(&self.counters.read_wait_nanos, read_latency);
+            let len = match read {
                 Ok(0) => {
-                    // Final numeric output when done
-                    if self.numeric_mode {
-                        self.output_numeric();
+                    Counters::mark_activity(
+                        &self.counters.last_read_nanos,
+                        self.progress.elapsed(),
+                    );
+                    let unterminated = self.line_mode.finish();
+                    self.advance_bar(unterminated);
+                    self.count_lines(unterminated);
+                    // Estimates are over once everything is read
+                    if self.expected_bytes.is_some() {
+                        self.progress.set_length(self.reporter.position());
                     }
+                    self.reporter.finish();
                     return Ok(written);
                 }
                 Ok(len) => {
-                    // Handle first byte logic
-                    if !self.first_byte_received {
-                        self.first_byte_received = true;
-
-                        // Handle delay start - wait specified seconds before showing output
-                        if let Some(delay_seconds) = self.delay_start {
-                            std::thread::sleep(std::time::Duration::from_secs_f64(delay_seconds));
-                        }
-
-                        // If wait for first byte is enabled, only now should we potentially show progress
-                        // (This is handled by checking first_byte_received in progress updates)
-                    }
-
+                    self.reporter.display_gate.first_byte();
+                    Counters::mark_activity(
+                        &self.counters.last_read_nanos,
+                        self.progress.elapsed(),
+                    );
                     len
                 }
                 Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                Err(_) if self.skip_input_errors => continue,
+                Err(_) if self.skip_input_errors => {
+                    self.counters.read_errors.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
                 Err(e) => return Err(e.into()),
             };
 
@@ -760,9 +2136,7 @@ impl PipeView {
                 let remaining = stop_size.saturating_sub(written);
                 if remaining == 0 {
                     // We've reached the stop size, finish
-                    if self.numeric_mode {
-                        self.output_numeric();
-                    }
+                    self.reporter.finish();
                     return Ok(written);
                 }
                 std::cmp::min(len, remaining as usize)
@@ -770,48 +2144,104 @@ impl PipeView {
                 len
             };
 
-            // Maybe skip output errors
-            match self.sink.write_all(&buf[..actual_len]) {
-                Ok(_) => (),
-                Err(_) if self.skip_output_errors => continue,
-                Err(e) => return Err(e.into()),
-            };
-            let transfer_unit = match self.line_mode {
-                LineMode::Line(delim) => {
-                    let lines = buf[..actual_len].iter().filter(|b| **b == delim).count() as u64;
-                    // Only update progress if we're past the wait-for-first-byte and delay period
-                    if !self.wait_for_first_byte || self.first_byte_received {
-                        self.progress.inc(lines);
+            // A line rate limit is paced one line at a time, so split the buffer after each line
+            let pace_by_line = self.limiter.is_some() && !matches!(self.line_mode, LineMode::Byte);
+            let write_wait_before = self.counters.write_wait_nanos.load(Ordering::Relaxed);
+            let throttle_before = self.counters.throttle_nanos.load(Ordering::Relaxed);
+            let mut rest = &buf[..actual_len];
+            while !rest.is_empty() {
+                // A replay writes the recorded chunk sizes, so stop at the end of the chunk
+                let mut window_len = match self.replay.as_ref().and_then(Replay::remaining) {
+                    Some(remaining) => rest.len().min(remaining),
+                    None => rest.len(),
+                };
+                // A byte rate limit releases the buffer a slice at a time
+                match self.limiter {
+                    Some(ref limiter) if !pace_by_line => {
+                        window_len = window_len.min(limiter.slice());
                     }
-                    lines
+                    _ => (),
                 }
-                LineMode::Byte => {
-                    // Only update progress if we're past the wait-for-first-byte and delay period
-                    if !self.wait_for_first_byte || self.first_byte_received {
-                        self.progress.inc(actual_len as u64);
+                let window = &rest[..window_len];
+                let (piece_len, transfer_unit) = if pace_by_line {
+                    match self.line_mode.next_end(window) {
+                        Some(end) => (end, 1),
+                        None => (window.len(), 0),
+                    }
+                } else {
+                    (window.len(), self.line_mode.count(window))
+                };
+                let piece = &rest[..piece_len];
+                rest = &rest[piece_len..];
+
+                // Hold each recorded chunk back until the time it arrived
+                let mut chunk_done = false;
+                if let Some(ref mut replay) = self.replay {
+                    let due = replay.due();
+                    chunk_done = replay.advance(piece_len);
+                    if let Some(due) = due {
+                        self.pause(due.saturating_sub(self.replay_start.elapsed()))?;
                     }
-                    actual_len as u64
                 }
-            };
+                if let Some(ref mut limiter) = self.limiter {
+                    let wait = limiter.take(transfer_unit);
+                    self.counters.set_limit(limiter.rate());
+                    self.pause(wait)?;
+                }
 
-            // Apply rate limiting
-            self.apply_rate_limit(transfer_unit);
+                // Maybe skip output errors
+                let write_start = Instant::now();
+                let write = self.sink.write_all(piece);
+                Counters::add_time(&self.counters.write_wait_nanos, write_start.elapsed());
+                match write {
+                    Ok(_) => (),
+                    Err(_) if self.skip_output_errors => {
+                        self.counters.write_errors.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                Counters::mark_activity(&self.counters.last_write_nanos, self.progress.elapsed());
+                self.advance_bar(transfer_unit);
+                self.counters
+                    .bytes
+                    .fetch_add(piece.len() as u64, Ordering::Relaxed);
+                match self.byte_mode_lines {
+                    Some(ref mut lines) => {
+                        let lines = lines.count(piece);
+                        self.count_lines(lines);
+                    }
+                    None => self.count_lines(transfer_unit),
+                }
 
-            // Output numeric values if in numeric mode (with throttling but always at least one)
-            if self.numeric_mode {
-                let now = std::time::Instant::now();
-                let should_output = self.numeric_output_count == 0
-                    || now.duration_since(self.last_numeric_output)
-                        >= std::time::Duration::from_millis(100);
+                written += piece.len() as u64;
+                self.update_line_length(written);
 
-                if should_output {
-                    self.output_numeric();
-                    self.last_numeric_output = now;
-                    self.numeric_output_count += 1;
+                // Pass each replayed chunk on whole, as it was recorded
+                if chunk_done {
+                    self.flush_sink()?;
                 }
             }
 
-            written += actual_len as u64;
+            if let Some(ref mut trace) = self.trace {
+                let since = |total: &AtomicU64, before: u64| {
+                    Duration::from_nanos(total.load(Ordering::Relaxed) - before)
+                };
+                // Tracing is best-effort, like the progress output
+                let _ = trace.record(&Chunk {
+                    read_at,
+                    bytes: actual_len,
+                    read_latency,
+                    write_latency: since(&self.counters.write_wait_nanos, write_wait_before),
+                    throttle: since(&self.counters.throttle_nanos, throttle_before),
+                    position: written,
+                });
+            }
+
+            // The next read may be the one that stalls
+            if self.flush_each_chunk {
+                self.flush_sink()?;
+            }
         }
     }
 }
@@ -0,0 +1,183 @@
+//! Prometheus metrics, served over HTTP or written for the textfile collector
+
+use crate::output::ProgressOutput;
+use crate::report::Snapshot;
+use std::fmt::Write as _;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// The latest metrics, kept ready for whoever asks
+pub struct Metrics {
+    /// Added as a `name` label to every metric
+    name: Option<String>,
+    /// Whether the expected size is in lines rather than bytes
+    counts_lines: bool,
+    rendered: Mutex<String>,
+    textfile: Option<Mutex<ProgressOutput>>,
+}
+
+impl Metrics {
+    pub fn new(
+        name: Option<String>,
+        counts_lines: bool,
+        textfile: Option<&str>,
+    ) -> io::Result<Arc<Metrics>> {
+        let textfile = match textfile {
+            Some(path) => Some(Mutex::new(ProgressOutput::status_file(path)?)),
+            None => None,
+        };
+        let metrics = Arc::new(Metrics {
+            name,
+            counts_lines,
+            rendered: Mutex::new(String::new()),
+            textfile,
+        });
+        metrics.publish(&Snapshot::default());
+        Ok(metrics)
+    }
+
+    /// Replace the metrics with ones for a new snapshot
+    pub fn publish(&self, snapshot: &Snapshot) {
+        let rendered = self.render(snapshot);
+        if let Some(ref textfile) = self.textfile {
+            // Metrics are best-effort, like the rest of the progress output
+            let _ = textfile.lock().unwrap().write_update(&rendered);
+        }
+        *self.rendered.lock().unwrap() = rendered;
+    }
+
+    /// Serve the metrics at /metrics on a background thread
+    pub fn serve(self: &Arc<Self>, addr: &str) -> io::Result<()> {
+        // Like Prometheus exporters, ":9100" means every interface
+        let addr = match addr.strip_prefix(':') {
+            Some(port) => format!("0.0.0.0:{port}"),
+            None => addr.to_string(),
+        };
+        let listener = TcpListener::bind(addr)?;
+        let metrics = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = metrics.respond(stream);
+            }
+        });
+        Ok(())
+    }
+
+    /// Answer one HTTP request
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        // A slow client mustn't hold up the next scrape for long
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+            match stream.read(&mut buf)? {
+                0 => break,
+                len => request.extend_from_slice(&buf[..len]),
+            }
+        }
+
+        let request_line = String::from_utf8_lossy(&request);
+        let mut parts = request_line.split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", self.rendered.lock().unwrap().clone()),
+            _ => ("404 Not Found", "Not found\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+
+    fn render(&self, snapshot: &Snapshot) -> String {
+        let labels = match self.name {
+            Some(ref name) => format!("name=\"{}\"", escape_label(name)),
+            None => String::new(),
+        };
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (extra, value) in samples {
+                let labels = [labels.as_str(), extra]
+                    .iter()
+                    .filter(|l| !l.is_empty())
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(",");
+                if labels.is_empty() {
+                    let _ = writeln!(out, "{name} {value}");
+                } else {
+                    let _ = writeln!(out, "{name}{{{labels}}} {value}");
+                }
+            }
+        };
+
+        metric(
+            "pv_transferred_bytes_total",
+            "counter",
+            "Bytes transferred.",
+            &[("", snapshot.bytes.to_string())],
+        );
+        metric(
+            "pv_transferred_lines_total",
+            "counter",
+            "Lines transferred.",
+            &[("", snapshot.lines.to_string())],
+        );
+        metric(
+            "pv_rate_bytes_per_second",
+            "gauge",
+            "Transfer rate since the previous update.",
+            &[("", format!("{:.1}", snapshot.rate))],
+        );
+        metric(
+            "pv_average_rate_bytes_per_second",
+            "gauge",
+            "Transfer rate over the whole transfer.",
+            &[("", format!("{:.1}", snapshot.avg_rate))],
+        );
+        metric(
+            "pv_skipped_errors_total",
+            "counter",
+            "Errors skipped with -E and -O.",
+            &[
+                ("direction=\"read\"", snapshot.read_errors.to_string()),
+                ("direction=\"write\"", snapshot.write_errors.to_string()),
+            ],
+        );
+        metric(
+            "pv_throttle_seconds_total",
+            "counter",
+            "Time spent sleeping to keep to the rate limit.",
+            &[("", format!("{:.3}", snapshot.throttle))],
+        );
+        if let Some(size) = snapshot.size {
+            let (name, help) = if self.counts_lines {
+                (
+                    "pv_expected_size_lines",
+                    "Expected size of the transfer in lines.",
+                )
+            } else {
+                (
+                    "pv_expected_size_bytes",
+                    "Expected size of the transfer in bytes.",
+                )
+            };
+            metric(name, "gauge", help, &[("", size.to_string())]);
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
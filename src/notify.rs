@@ -0,0 +1,75 @@
+//! Service notifications for systemd units, sent to `NOTIFY_SOCKET`
+
+use std::time::Duration;
+
+pub struct Notifier {
+    #[cfg(unix)]
+    socket: std::os::unix::net::UnixDatagram,
+}
+
+impl Notifier {
+    /// Connect to the socket systemd gives in `NOTIFY_SOCKET`, if pv runs under it
+    #[cfg(unix)]
+    pub fn from_env() -> Option<Notifier> {
+        use std::os::unix::net::UnixDatagram;
+        let path = std::env::var_os("NOTIFY_SOCKET")?;
+        let socket = UnixDatagram::unbound().ok()?;
+        let path = path.to_str()?;
+        match path.strip_prefix('@') {
+            Some(name) => connect_abstract(&socket, name).ok()?,
+            None => socket.connect(path).ok()?,
+        }
+        Some(Notifier { socket })
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_env() -> Option<Notifier> {
+        None
+    }
+
+    /// How often systemd expects a watchdog ping, if it wants them from this process
+    pub fn watchdog_interval() -> Option<Duration> {
+        let pid = std::env::var("WATCHDOG_PID").ok();
+        if pid.is_some_and(|pid| pid != std::process::id().to_string()) {
+            return None;
+        }
+        let usec = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+        Some(Duration::from_micros(usec))
+    }
+
+    /// Send a raw notification, like `READY=1`
+    pub fn notify(&self, state: &str) {
+        // systemd may have gone away, which mustn't stop the transfer
+        #[cfg(unix)]
+        let _ = self.socket.send(state.as_bytes());
+        #[cfg(not(unix))]
+        let _ = state;
+    }
+
+    /// Show a status line, and ping the watchdog unless the transfer has stalled
+    pub fn status(&self, line: &str, stalled: bool) {
+        let mut state = format!("STATUS={}", line.replace('\n', " "));
+        if !stalled {
+            state.push_str("\nWATCHDOG=1");
+        }
+        self.notify(&state);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(socket: &std::os::unix::net::UnixDatagram, name: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    socket.connect_addr(&addr)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn connect_abstract(
+    _socket: &std::os::unix::net::UnixDatagram,
+    _name: &str,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract sockets are only supported on Linux",
+    ))
+}
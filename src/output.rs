@@ -0,0 +1,296 @@
+//! Where progress is written: stderr, another file descriptor, or a status file
+
+use indicatif::TermLike;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Width the bar is drawn at, since the destination may not be a terminal
+const BAR_WIDTH: u16 = 80;
+
+/// A destination for progress updates
+pub enum ProgressOutput {
+    /// Updates are appended, one per line
+    Stream(Box<dyn Write + Send>),
+    /// The file is replaced on every update, so it only ever holds the latest one
+    StatusFile {
+        path: PathBuf,
+        /// The update being drawn by the bar, until it is flushed
+        pending: String,
+    },
+}
+
+impl ProgressOutput {
+    pub fn stderr() -> ProgressOutput {
+        ProgressOutput::Stream(Box::new(io::stderr()))
+    }
+
+    /// Write to a file descriptor given on the command line
+    pub fn fd(fd: i32) -> io::Result<ProgressOutput> {
+        if fd == 2 {
+            return Ok(ProgressOutput::stderr());
+        }
+        Ok(ProgressOutput::Stream(Box::new(open_fd(fd)?)))
+    }
+
+    /// Keep the latest update in a file, which is created empty straight away
+    pub fn status_file(path: impl Into<PathBuf>) -> io::Result<ProgressOutput> {
+        let path = path.into();
+        File::create(&path)?;
+        Ok(ProgressOutput::StatusFile {
+            path,
+            pending: String::new(),
+        })
+    }
+
+    /// Write one complete update, like a numeric line or JSON record
+    pub fn write_update(&mut self, update: &str) -> io::Result<()> {
+        match self {
+            ProgressOutput::Stream(stream) => {
+                writeln!(stream, "{update}")?;
+                stream.flush()
+            }
+            ProgressOutput::StatusFile { pending, .. } => {
+                pending.clear();
+                pending.push_str(update);
+                self.flush()
+            }
+        }
+    }
+
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        match self {
+            ProgressOutput::Stream(stream) => stream.write_all(s.as_bytes()),
+            ProgressOutput::StatusFile { pending, .. } => {
+                pending.push_str(s);
+                Ok(())
+            }
+        }
+    }
+
+    /// Send on what has been written; a status file is replaced with the pending update
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ProgressOutput::Stream(stream) => stream.flush(),
+            // The bar clears itself before going away, but the last update should stay
+            ProgressOutput::StatusFile { pending, .. } if pending.trim().is_empty() => Ok(()),
+            ProgressOutput::StatusFile { path, pending } => {
+                // Renaming over the old file means readers never see a partial update
+                let mut temp_path = path.clone().into_os_string();
+                temp_path.push(".tmp");
+                let mut temp = File::create(&temp_path)?;
+                writeln!(temp, "{}", pending.trim_end())?;
+                drop(temp);
+                std::fs::rename(&temp_path, path)
+            }
+        }
+    }
+
+    /// Terminal escapes only make sense on a stream
+    fn write_escape(&mut self, escape: &str) -> io::Result<()> {
+        match self {
+            ProgressOutput::Stream(stream) => stream.write_all(escape.as_bytes()),
+            ProgressOutput::StatusFile { .. } => Ok(()),
+        }
+    }
+}
+
+/// Lets the visual bar draw to a `ProgressOutput`
+#[derive(Clone)]
+pub struct BarTerm(pub Arc<Mutex<ProgressOutput>>);
+
+impl std::fmt::Debug for BarTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BarTerm")
+    }
+}
+
+impl BarTerm {
+    fn move_cursor(&self, n: usize, direction: char) -> io::Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        self.0
+            .lock()
+            .unwrap()
+            .write_escape(&format!("\x1b[{n}{direction}"))
+    }
+}
+
+impl TermLike for BarTerm {
+    fn width(&self) -> u16 {
+        BAR_WIDTH
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        self.move_cursor(n, 'A')
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        self.move_cursor(n, 'B')
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        self.move_cursor(n, 'C')
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        self.move_cursor(n, 'D')
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        self.0.lock().unwrap().write_str(&format!("{s}\n"))
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.0.lock().unwrap().write_str(s)
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        let mut output = self.0.lock().unwrap();
+        if let ProgressOutput::StatusFile { pending, .. } = &mut *output {
+            pending.clear();
+        }
+        output.write_escape("\r\x1b[2K")
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// Keeps the bar's latest frame instead of drawing it, for outputs that take a line at a time
+#[derive(Debug, Default)]
+pub struct LogTerm {
+    pub frame: Arc<Mutex<String>>,
+    /// The frame being drawn, until it is flushed
+    pending: Mutex<String>,
+}
+
+impl LogTerm {
+    pub fn new(frame: Arc<Mutex<String>>) -> LogTerm {
+        LogTerm {
+            frame,
+            pending: Mutex::default(),
+        }
+    }
+}
+
+impl TermLike for LogTerm {
+    fn width(&self) -> u16 {
+        BAR_WIDTH
+    }
+
+    fn move_cursor_up(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_down(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_right(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_left(&self, _n: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push_str(s);
+        pending.push('\n');
+        Ok(())
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.pending.lock().unwrap().push_str(s);
+        Ok(())
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.pending.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let pending = self.pending.lock().unwrap();
+        // Like a status file, keep the last frame when the bar clears itself
+        if !pending.trim().is_empty() {
+            *self.frame.lock().unwrap() = pending.trim_end().to_string();
+        }
+        Ok(())
+    }
+}
+
+/// The current UTC time as `YYYY-MM-DDTHH:MM:SSZ`
+pub fn log_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm
+    let shifted = days + 719468;
+    let era = shifted / 146097;
+    let day_of_era = shifted % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Whether a file descriptor given on the command line is a terminal
+pub fn is_terminal(fd: i32) -> bool {
+    use std::io::IsTerminal;
+    if fd == 2 {
+        return io::stderr().is_terminal();
+    }
+    fd_is_terminal(fd)
+}
+
+#[cfg(unix)]
+fn fd_is_terminal(fd: i32) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+#[cfg(not(unix))]
+fn fd_is_terminal(_fd: i32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn open_fd(fd: i32) -> io::Result<File> {
+    use std::os::unix::io::FromRawFd;
+    // Only take ownership of descriptors that are really open
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(not(unix))]
+fn open_fd(_fd: i32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file descriptors are only supported on Unix",
+    ))
+}
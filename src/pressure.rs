@@ -0,0 +1,96 @@
+//! How busy the system is, for backing off the rate limit under pressure
+
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Where Linux reports pressure stalls on I/O
+pub const PSI_IO: &str = "/proc/pressure/io";
+
+pub enum PressureSource {
+    /// A file holding a number, or in the format of /proc/pressure
+    File(String),
+    /// A shell command that prints a number
+    Command(String),
+}
+
+/// The latest pressure, kept up to date on a background thread
+pub struct Pressure {
+    /// The bits of an f64
+    latest: Arc<AtomicU64>,
+}
+
+impl Pressure {
+    /// Read the pressure once now, then every `interval`
+    pub fn watch(source: PressureSource, interval: Duration) -> Result<Pressure, String> {
+        let latest = Arc::new(AtomicU64::new(source.read()?.to_bits()));
+        let pressure = latest.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            // Keep the last reading if one goes wrong
+            if let Ok(reading) = source.read() {
+                pressure.store(reading.to_bits(), Ordering::Relaxed);
+            }
+        });
+        Ok(Pressure { latest })
+    }
+
+    pub fn latest(&self) -> f64 {
+        f64::from_bits(self.latest.load(Ordering::Relaxed))
+    }
+}
+
+impl PressureSource {
+    fn read(&self) -> Result<f64, String> {
+        let text = match self {
+            PressureSource::File(path) => {
+                std::fs::read_to_string(path).map_err(|e| e.to_string())?
+            }
+            PressureSource::Command(command) => {
+                let output = shell(command).output().map_err(|e| e.to_string())?;
+                if !output.status.success() {
+                    return Err(format!("command failed with {}", output.status));
+                }
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+        };
+        parse_pressure(&text).ok_or_else(|| "no pressure reading found".to_string())
+    }
+}
+
+impl std::fmt::Display for PressureSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PressureSource::File(path) | PressureSource::Command(path) => write!(f, "{path}"),
+        }
+    }
+}
+
+/// The share of time stalled over the last 10 seconds from /proc/pressure,
+/// or else the first number, allowing a trailing %
+fn parse_pressure(text: &str) -> Option<f64> {
+    let reading = match text.split_once("avg10=") {
+        Some((_, psi)) => psi.split_whitespace().next()?,
+        None => text.split_whitespace().next()?.trim_end_matches('%'),
+    };
+    reading
+        .parse()
+        .ok()
+        .filter(|pressure: &f64| pressure.is_finite())
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
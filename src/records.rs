@@ -0,0 +1,257 @@
+//! Counting records in a stream that arrives in arbitrary chunks
+
+/// A record delimiter of one or more bytes.
+///
+/// Matches are counted without overlapping, and the match state is carried
+/// between calls to `count` so delimiters split across reads still count once.
+#[derive(Debug, Clone)]
+pub struct Delimiter {
+    pattern: Vec<u8>,
+    /// KMP failure function: longest proper prefix of `pattern[..=i]` that is also a suffix
+    failure: Vec<usize>,
+    /// How much of `pattern` the end of the last chunk matched
+    matched: usize,
+}
+
+impl Delimiter {
+    pub fn new(pattern: Vec<u8>) -> Delimiter {
+        assert!(!pattern.is_empty(), "Delimiter cannot be empty");
+        let mut failure = vec![0; pattern.len()];
+        let mut k = 0;
+        for i in 1..pattern.len() {
+            while k > 0 && pattern[i] != pattern[k] {
+                k = failure[k - 1];
+            }
+            if pattern[i] == pattern[k] {
+                k += 1;
+            }
+            failure[i] = k;
+        }
+        Delimiter {
+            pattern,
+            failure,
+            matched: 0,
+        }
+    }
+
+    /// Count the delimiters that end within `buf`
+    pub fn count(&mut self, buf: &[u8]) -> u64 {
+        if let [byte] = self.pattern[..] {
+            return buf.iter().filter(|b| **b == byte).count() as u64;
+        }
+        count_ends(buf, |rest| self.next_end(rest))
+    }
+
+    /// Consume `buf` up to the end of the first delimiter in it, returning where that is
+    pub fn next_end(&mut self, buf: &[u8]) -> Option<usize> {
+        for (i, &b) in buf.iter().enumerate() {
+            while self.matched > 0 && b != self.pattern[self.matched] {
+                self.matched = self.failure[self.matched - 1];
+            }
+            if b == self.pattern[self.matched] {
+                self.matched += 1;
+            }
+            if self.matched == self.pattern.len() {
+                self.matched = 0;
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+}
+
+/// Count how many times `next_end` finds the end of a unit in `buf`
+fn count_ends(mut buf: &[u8], mut next_end: impl FnMut(&[u8]) -> Option<usize>) -> u64 {
+    let mut found = 0;
+    while let Some(end) = next_end(buf) {
+        found += 1;
+        buf = &buf[end..];
+    }
+    found
+}
+
+/// Parse a delimiter given on the command line.
+///
+/// Accepts literal text plus the escapes `\n`, `\r`, `\t`, `\0`, `\\` and `\xHH`.
+pub fn parse_delimiter(s: &str) -> Result<Delimiter, String> {
+    let mut pattern = Vec::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            let mut utf8 = [0; 4];
+            pattern.extend_from_slice(ch.encode_utf8(&mut utf8).as_bytes());
+            continue;
+        }
+        let byte = match chars.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 {
+                    return Err(format!("Invalid escape: \\x{hex}. Use two hex digits"));
+                }
+                u8::from_str_radix(&hex, 16).map_err(|_| format!("Invalid escape: \\x{hex}"))?
+            }
+            Some(other) => return Err(format!("Invalid escape: \\{other}")),
+            None => return Err("Delimiter cannot end with a lone backslash".to_string()),
+        };
+        pattern.push(byte);
+    }
+
+    if pattern.is_empty() {
+        return Err("Delimiter cannot be empty".to_string());
+    }
+    Ok(Delimiter::new(pattern))
+}
+
+/// Record framings that take more than a delimiter to count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecordFormat {
+    /// CSV rows, where quoted fields may contain newlines
+    Csv,
+    /// JSON Lines, skipping blank lines
+    Jsonl,
+    /// Records prefixed by their length as a 4-byte big-endian integer
+    U32beLength,
+    /// Records prefixed by their length as a protobuf-style varint
+    VarintLength,
+}
+
+/// Counts whole records of a `RecordFormat`, carrying framing state between chunks
+#[derive(Debug, Clone)]
+pub struct RecordCounter {
+    format: RecordFormat,
+    /// Text formats: inside a quoted CSV field
+    in_quotes: bool,
+    /// Text formats: the current record has content but no terminator yet
+    pending: bool,
+    /// Length formats: the length prefix decoded so far
+    prefix: u64,
+    /// Length formats: how many bytes of the length prefix have been read
+    prefix_len: u32,
+    /// Length formats: payload bytes left in the current record, once the prefix is complete
+    remaining: Option<u64>,
+}
+
+impl RecordCounter {
+    pub fn new(format: RecordFormat) -> RecordCounter {
+        RecordCounter {
+            format,
+            in_quotes: false,
+            pending: false,
+            prefix: 0,
+            prefix_len: 0,
+            remaining: None,
+        }
+    }
+
+    /// Count the records that end within `buf`
+    pub fn count(&mut self, buf: &[u8]) -> u64 {
+        count_ends(buf, |rest| self.next_end(rest))
+    }
+
+    /// Consume `buf` up to the end of the first record in it, returning where that is
+    pub fn next_end(&mut self, buf: &[u8]) -> Option<usize> {
+        match self.format {
+            RecordFormat::Csv => self.next_end_csv(buf),
+            RecordFormat::Jsonl => self.next_end_jsonl(buf),
+            RecordFormat::U32beLength | RecordFormat::VarintLength => self.next_end_length(buf),
+        }
+    }
+
+    /// Count a final record that wasn't terminated before the end of the stream
+    pub fn finish(&mut self) -> u64 {
+        let unterminated = match self.format {
+            RecordFormat::Csv | RecordFormat::Jsonl => self.pending,
+            RecordFormat::U32beLength | RecordFormat::VarintLength => false,
+        };
+        *self = RecordCounter::new(self.format);
+        unterminated as u64
+    }
+
+    fn next_end_csv(&mut self, buf: &[u8]) -> Option<usize> {
+        for (i, &b) in buf.iter().enumerate() {
+            match b {
+                // A doubled quote toggles twice, so escapes need no special care
+                b'"' => self.in_quotes = !self.in_quotes,
+                b'\n' if !self.in_quotes => {
+                    if std::mem::take(&mut self.pending) {
+                        return Some(i + 1);
+                    }
+                    continue;
+                }
+                b'\r' if !self.in_quotes => continue,
+                _ => (),
+            }
+            self.pending = true;
+        }
+        None
+    }
+
+    fn next_end_jsonl(&mut self, buf: &[u8]) -> Option<usize> {
+        // JSON strings can't hold raw newlines, so every non-blank line is a record
+        for (i, &b) in buf.iter().enumerate() {
+            if b == b'\n' {
+                if std::mem::take(&mut self.pending) {
+                    return Some(i + 1);
+                }
+            } else if !b.is_ascii_whitespace() {
+                self.pending = true;
+            }
+        }
+        None
+    }
+
+    fn next_end_length(&mut self, buf: &[u8]) -> Option<usize> {
+        let mut pos = 0;
+        while pos < buf.len() {
+            match self.remaining {
+                Some(remaining) => {
+                    let skip = remaining.min((buf.len() - pos) as u64);
+                    pos += skip as usize;
+                    self.remaining = Some(remaining - skip);
+                }
+                None => {
+                    self.read_prefix_byte(buf[pos]);
+                    pos += 1;
+                }
+            }
+            if self.remaining == Some(0) {
+                self.remaining = None;
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    fn read_prefix_byte(&mut self, b: u8) {
+        match self.format {
+            RecordFormat::U32beLength => {
+                self.prefix = (self.prefix << 8) | b as u64;
+                self.prefix_len += 1;
+                if self.prefix_len == 4 {
+                    self.start_payload();
+                }
+            }
+            _ => {
+                // Ten bytes hold all 64 bits, so malformed varints longer than that lose the rest
+                if self.prefix_len < 10 {
+                    self.prefix |= ((b & 0x7f) as u64) << (7 * self.prefix_len);
+                    self.prefix_len += 1;
+                }
+                if b & 0x80 == 0 {
+                    self.start_payload();
+                }
+            }
+        }
+    }
+
+    fn start_payload(&mut self) {
+        self.remaining = Some(self.prefix);
+        self.prefix = 0;
+        self.prefix_len = 0;
+    }
+}
@@ -0,0 +1,79 @@
+//! Point-in-time views of a transfer for the machine-readable outputs
+
+use std::fmt::Write;
+
+/// The state of the transfer at one update
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Seconds since the transfer started
+    pub elapsed: f64,
+    pub bytes: u64,
+    pub lines: u64,
+    /// Bytes per second since the previous snapshot
+    pub rate: f64,
+    /// Bytes per second over the whole transfer
+    pub avg_rate: f64,
+    /// Percentage complete, when the size is known
+    pub percent: Option<f64>,
+    /// Seconds left, when the size is known
+    pub eta: Option<f64>,
+    pub read_errors: u64,
+    pub write_errors: u64,
+    /// Seconds spent sleeping for the rate limit
+    pub throttle: f64,
+    /// Expected total, in whatever the bar counts, when the size is known
+    pub size: Option<u64>,
+}
+
+impl Snapshot {
+    /// Render as a single line of JSON, with error totals on the final record
+    pub fn to_json(&self, name: Option<&str>, done: bool) -> String {
+        let mut json = String::from("{");
+        let _ = write!(json, "\"elapsed\":{:.3}", self.elapsed);
+        let _ = write!(json, ",\"bytes\":{}", self.bytes);
+        let _ = write!(json, ",\"lines\":{}", self.lines);
+        let _ = write!(json, ",\"rate\":{:.1}", self.rate);
+        let _ = write!(json, ",\"avg_rate\":{:.1}", self.avg_rate);
+        let _ = write!(json, ",\"percent\":{}", json_number(self.percent, 2));
+        let _ = write!(json, ",\"eta\":{}", json_number(self.eta, 1));
+        let _ = write!(
+            json,
+            ",\"name\":{}",
+            name.map_or("null".into(), json_string)
+        );
+        let _ = write!(json, ",\"done\":{done}");
+        if done {
+            let _ = write!(json, ",\"read_errors\":{}", self.read_errors);
+            let _ = write!(json, ",\"write_errors\":{}", self.write_errors);
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn json_number(value: Option<f64>, precision: usize) -> String {
+    match value {
+        Some(value) if value.is_finite() => format!("{value:.precision$}"),
+        _ => "null".to_string(),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", ch as u32);
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,175 @@
+//! Rate limits that change with the time of day
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Rules like `Mon-Fri 08:00-18:00 20M`, where the first that matches sets the rate
+#[derive(Debug, Clone)]
+pub struct Schedule<R> {
+    rules: Vec<Rule<R>>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule<R> {
+    /// One bit for each day, starting with Monday
+    days: u8,
+    /// Minutes into the day, from the start until just before the end
+    hours: Option<(u32, u32)>,
+    /// None when unlimited
+    rate: Option<R>,
+}
+
+impl<R> Schedule<R> {
+    /// Parse rules separated by semicolons or newlines, with `#` comments
+    pub fn parse(s: &str, parse_rate: fn(&str) -> Result<R, String>) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in s.split([';', '\n']) {
+            let rule = rule.split('#').next().unwrap_or("").trim();
+            if rule.is_empty() {
+                continue;
+            }
+            rules.push(Rule::parse(rule, parse_rate).map_err(|e| format!("{e} in '{rule}'"))?);
+        }
+        if rules.is_empty() {
+            return Err("Rate schedule has no rules".to_string());
+        }
+        Ok(Schedule { rules })
+    }
+
+    /// Convert the rates, as once -k and -8 are known
+    pub fn map<S>(self, f: impl Fn(R) -> S) -> Schedule<S> {
+        Schedule {
+            rules: self
+                .rules
+                .into_iter()
+                .map(|rule| Rule {
+                    days: rule.days,
+                    hours: rule.hours,
+                    rate: rule.rate.map(&f),
+                })
+                .collect(),
+        }
+    }
+
+    /// The rate from the first rule that matches the local time now, None meaning
+    /// no rule matches and Some(None) that the matching rule is unlimited
+    pub fn rate_now(&self) -> Option<Option<&R>> {
+        let (day, minute) = local_time();
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(day, minute))
+            .map(|rule| rule.rate.as_ref())
+    }
+}
+
+impl<R> Rule<R> {
+    /// Parse `[DAYS] [HH:MM-HH:MM] RATE`, where DAYS is `*`, `Sat,Sun` or `Mon-Fri`
+    fn parse(rule: &str, parse_rate: fn(&str) -> Result<R, String>) -> Result<Self, String> {
+        let mut words: Vec<&str> = rule.split_whitespace().collect();
+        let rate = match words.pop() {
+            Some(rate) if rate.eq_ignore_ascii_case("unlimited") => None,
+            Some(rate) => Some(parse_rate(rate)?),
+            None => return Err("Missing rate".to_string()),
+        };
+        let mut days = 0x7f;
+        let mut hours = None;
+        for word in words {
+            if word.contains(':') {
+                hours = Some(parse_hours(word)?);
+            } else if word != "*" {
+                days = parse_days(word)?;
+            }
+        }
+        Ok(Rule { days, hours, rate })
+    }
+
+    fn matches(&self, day: u32, minute: u32) -> bool {
+        let on = |day: u32| self.days & (1 << day) != 0;
+        match self.hours {
+            None => on(day),
+            Some((start, end)) if start <= end => on(day) && (start..end).contains(&minute),
+            // Overnight, like 22:00-06:00, where the hours after midnight belong to the day before
+            Some((start, end)) => {
+                (on(day) && minute >= start) || (on((day + 6) % 7) && minute < end)
+            }
+        }
+    }
+}
+
+/// Days like `Mon`, `Sat,Sun` or `Mon-Fri`, as a bit for each
+fn parse_days(word: &str) -> Result<u8, String> {
+    let day = |name: &str| {
+        DAYS.iter()
+            .position(|day| name.eq_ignore_ascii_case(day))
+            .ok_or_else(|| format!("Invalid day: {name}"))
+    };
+    let mut days = 0;
+    for part in word.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut day, last) = (day(first)?, day(last)?);
+                // Ranges may wrap around the weekend, like Fri-Mon
+                loop {
+                    days |= 1 << day;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days |= 1 << day(part)?,
+        }
+    }
+    Ok(days)
+}
+
+/// A range like `08:00-18:00`, in minutes into the day
+fn parse_hours(word: &str) -> Result<(u32, u32), String> {
+    let minute = |time: &str| {
+        let (hour, minute) = time
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid time: {time}"))?;
+        match (hour.parse::<u32>(), minute.parse::<u32>()) {
+            // Checking the hour first keeps huge ones from overflowing
+            (Ok(hour), Ok(minute))
+                if hour <= 24 && minute < 60 && hour * 60 + minute <= 24 * 60 =>
+            {
+                Ok(hour * 60 + minute)
+            }
+            _ => Err(format!("Invalid time: {time}")),
+        }
+    };
+    let (start, end) = word
+        .split_once('-')
+        .ok_or_else(|| format!("Invalid time range: {word}"))?;
+    Ok((minute(start)?, minute(end)?))
+}
+
+/// The local weekday, counting from Monday, and the minute of the day
+#[cfg(unix)]
+fn local_time() -> (u32, u32) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs()) as libc::time_t;
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm
+    };
+    (
+        (tm.tm_wday as u32 + 6) % 7,
+        (tm.tm_hour * 60 + tm.tm_min) as u32,
+    )
+}
+
+/// Without the C library's time zones, schedules follow UTC
+#[cfg(not(unix))]
+fn local_time() -> (u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = secs / 86400;
+    // 1970-01-01 was a Thursday
+    (((days + 3) % 7) as u32, (secs % 86400 / 60) as u32)
+}
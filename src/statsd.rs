@@ -0,0 +1,76 @@
+//! StatsD metrics sent over UDP
+
+use crate::report::Snapshot;
+use std::fmt::Write as _;
+use std::io;
+use std::net::UdpSocket;
+use std::sync::Mutex;
+
+pub struct StatsD {
+    socket: UdpSocket,
+    prefix: String,
+    /// Bytes and lines already counted, since StatsD counters take increments
+    sent: Mutex<(u64, u64)>,
+}
+
+impl StatsD {
+    /// Send to a `HOST:PORT` under `prefix`, or one made from the -N name
+    pub fn connect(addr: &str, prefix: Option<&str>, name: Option<&str>) -> io::Result<StatsD> {
+        let socket = UdpSocket::bind(if addr.starts_with('[') {
+            "[::]:0"
+        } else {
+            "0.0.0.0:0"
+        })?;
+        socket.connect(addr)?;
+        let prefix = match (prefix, name) {
+            (Some(prefix), _) => prefix.to_string(),
+            (None, Some(name)) => format!("pv.{}", metric_name(name)),
+            (None, None) => "pv".to_string(),
+        };
+        Ok(StatsD {
+            socket,
+            prefix,
+            sent: Mutex::new((0, 0)),
+        })
+    }
+
+    /// Send one snapshot as a single packet
+    pub fn publish(&self, snapshot: &Snapshot) {
+        let (sent_bytes, sent_lines) = std::mem::replace(
+            &mut *self.sent.lock().unwrap(),
+            (snapshot.bytes, snapshot.lines),
+        );
+        let prefix = &self.prefix;
+
+        let mut packet = String::new();
+        let _ = writeln!(
+            packet,
+            "{prefix}.bytes:{}|c",
+            snapshot.bytes.saturating_sub(sent_bytes)
+        );
+        let _ = writeln!(
+            packet,
+            "{prefix}.lines:{}|c",
+            snapshot.lines.saturating_sub(sent_lines)
+        );
+        let _ = writeln!(packet, "{prefix}.rate:{:.1}|g", snapshot.rate);
+        if let Some(percent) = snapshot.percent {
+            let _ = writeln!(packet, "{prefix}.percent:{percent:.2}|g");
+        }
+        // Nobody may be listening, and that mustn't stop the transfer
+        let _ = self.socket.send(packet.trim_end().as_bytes());
+    }
+}
+
+/// Make a name safe to use in a StatsD metric name
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
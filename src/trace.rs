@@ -0,0 +1,162 @@
+//! A record of every chunk through the transfer, for analysis or replaying afterwards
+
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// How the trace file is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TraceFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// The timings of one chunk, from reading it to passing it on
+pub struct Chunk {
+    /// When the read finished
+    pub read_at: Instant,
+    pub bytes: usize,
+    pub read_latency: Duration,
+    pub write_latency: Duration,
+    pub throttle: Duration,
+    /// Bytes written so far, including this chunk
+    pub position: u64,
+}
+
+pub struct Tracer {
+    file: File,
+    format: TraceFormat,
+    start: Instant,
+}
+
+impl Tracer {
+    pub fn create(path: &str, format: TraceFormat, start: Instant) -> io::Result<Tracer> {
+        let mut file = File::create(path)?;
+        if format == TraceFormat::Csv {
+            writeln!(
+                file,
+                "time,bytes,read_latency,write_latency,throttle,position"
+            )?;
+        }
+        Ok(Tracer {
+            file,
+            format,
+            start,
+        })
+    }
+
+    /// Add a row for a chunk, written straight out so it survives an abrupt exit
+    pub fn record(&mut self, chunk: &Chunk) -> io::Result<()> {
+        let time = chunk.read_at.saturating_duration_since(self.start);
+        let row = match self.format {
+            TraceFormat::Csv => format!(
+                "{:.6},{},{:.6},{:.6},{:.6},{}\n",
+                time.as_secs_f64(),
+                chunk.bytes,
+                chunk.read_latency.as_secs_f64(),
+                chunk.write_latency.as_secs_f64(),
+                chunk.throttle.as_secs_f64(),
+                chunk.position
+            ),
+            TraceFormat::Ndjson => format!(
+                "{{\"time\":{:.6},\"bytes\":{},\"read_latency\":{:.6},\"write_latency\":{:.6},\"throttle\":{:.6},\"position\":{}}}\n",
+                time.as_secs_f64(),
+                chunk.bytes,
+                chunk.read_latency.as_secs_f64(),
+                chunk.write_latency.as_secs_f64(),
+                chunk.throttle.as_secs_f64(),
+                chunk.position
+            ),
+        };
+        self.file.write_all(row.as_bytes())
+    }
+}
+
+/// The chunk sizes and times of a recorded trace, for reproducing them
+pub struct Replay {
+    /// When each chunk is due, and how big it is
+    chunks: Vec<(Duration, usize)>,
+    /// The chunk being written
+    next: usize,
+    /// How much of that chunk has been written
+    written: usize,
+}
+
+impl Replay {
+    /// Read a trace written by --trace, in either format
+    pub fn load(path: &str) -> io::Result<Replay> {
+        let trace = std::fs::read_to_string(path)?;
+        let mut chunks = Vec::new();
+        for (number, line) in trace.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("time,") {
+                continue;
+            }
+            let fields = if line.starts_with('{') {
+                json_field(line, "time").zip(json_field(line, "bytes"))
+            } else {
+                let mut fields = line.split(',');
+                fields.next().zip(fields.next())
+            };
+            let chunk = fields.and_then(|(time, bytes)| {
+                let time = Duration::try_from_secs_f64(time.trim().parse().ok()?).ok()?;
+                Some((time, bytes.trim().parse().ok()?))
+            });
+            match chunk {
+                Some((_, 0)) => (),
+                Some(chunk) => chunks.push(chunk),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {} is not a trace row", number + 1),
+                    ))
+                }
+            }
+        }
+        Ok(Replay {
+            chunks,
+            next: 0,
+            written: 0,
+        })
+    }
+
+    /// When the next bytes are due, if they start a chunk
+    pub fn due(&self) -> Option<Duration> {
+        if self.written > 0 {
+            return None;
+        }
+        self.chunks.get(self.next).map(|(time, _)| *time)
+    }
+
+    /// Bytes left in the current chunk, or None once the trace has run out
+    pub fn remaining(&self) -> Option<usize> {
+        self.chunks
+            .get(self.next)
+            .map(|(_, bytes)| bytes - self.written)
+    }
+
+    /// Count bytes written, returning whether they finished a chunk
+    pub fn advance(&mut self, bytes: usize) -> bool {
+        let Some(remaining) = self.remaining() else {
+            return false;
+        };
+        if bytes < remaining {
+            self.written += bytes;
+            return false;
+        }
+        self.next += 1;
+        self.written = 0;
+        true
+    }
+}
+
+/// The raw value of a top-level number field in a line of our own NDJSON
+fn json_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("\"{key}\":"))? + key.len() + 3;
+    let value = &line[start..];
+    let end = value.find([',', '}']).unwrap_or(value.len());
+    Some(&value[..end])
+}
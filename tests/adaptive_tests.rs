@@ -0,0 +1,176 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command as StdCommand, Stdio};
+use tempfile::NamedTempFile;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// The limits shown every half second while sending `size` bytes
+fn limits(args: &[&str], size: usize) -> Vec<f64> {
+    let output = pv_cmd()
+        .args(["-n", "-i", "0.5", "-F", "%{limit}", "-L", "4k"])
+        .args(args)
+        .write_stdin("a".repeat(size))
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim().parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_adaptive_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--adaptive"))
+        .stdout(predicate::str::contains("--pressure-file"))
+        .stdout(predicate::str::contains("--pressure-command"))
+        .stdout(predicate::str::contains("--pressure-threshold"));
+}
+
+#[test]
+fn test_adaptive_requires_limit() {
+    pv_cmd()
+        .arg("--adaptive")
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--rate-limit"));
+}
+
+#[test]
+fn test_adaptive_backs_off_under_pressure() {
+    let pressure = create_test_file(b"some avg10=50.00 avg60=20.00 avg300=5.00 total=123456\n");
+    let path = pressure.path().to_str().unwrap();
+
+    let limits = limits(&["--adaptive", "--pressure-file", path], 8000);
+    assert_eq!(limits.first(), Some(&4096.0), "{:?}", limits);
+    assert!(limits.iter().any(|&limit| limit <= 2048.0), "{:?}", limits);
+}
+
+#[test]
+fn test_adaptive_keeps_limit_without_pressure() {
+    let pressure = create_test_file(b"1.5%\n");
+    let path = pressure.path().to_str().unwrap();
+
+    let limits = limits(&["--adaptive", "--pressure-file", path], 6000);
+    assert!(limits.iter().all(|&limit| limit == 4096.0), "{:?}", limits);
+}
+
+#[test]
+fn test_adaptive_threshold() {
+    let pressure = create_test_file(b"30\n");
+    let path = pressure.path().to_str().unwrap();
+
+    let args = [
+        "--adaptive",
+        "--pressure-file",
+        path,
+        "--pressure-threshold",
+        "40",
+    ];
+    let limits = limits(&args, 6000);
+    assert!(limits.iter().all(|&limit| limit == 4096.0), "{:?}", limits);
+}
+
+#[test]
+fn test_adaptive_recovers() {
+    let pressure = create_test_file(b"90\n");
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args([
+            "-n",
+            "-i",
+            "0.5",
+            "-F",
+            "%{limit}",
+            "-L",
+            "4k",
+            "--adaptive",
+        ])
+        .arg("--pressure-file")
+        .arg(pressure.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(&[b'a'; 12000]).unwrap();
+    drop(stdin);
+
+    // The pressure eases after the first back-off
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    fs::write(pressure.path(), "0\n").unwrap();
+
+    let limits: Vec<f64> = BufReader::new(child.stderr.take().unwrap())
+        .lines()
+        .map(|line| line.unwrap().trim().parse().unwrap())
+        .collect();
+    assert!(child.wait().unwrap().success());
+
+    let lowest = limits
+        .iter()
+        .position(|&limit| limit < 4096.0)
+        .unwrap_or_else(|| panic!("Never backed off: {:?}", limits));
+    assert!(
+        limits[lowest..].windows(2).any(|pair| pair[1] > pair[0]),
+        "Never recovered: {:?}",
+        limits
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_adaptive_pressure_command() {
+    let limits = limits(&["--adaptive", "--pressure-command", "echo 80"], 8000);
+    assert!(limits.iter().any(|&limit| limit <= 2048.0), "{:?}", limits);
+}
+
+#[test]
+fn test_adaptive_shown_in_bar() {
+    let pressure = create_test_file(b"0\n");
+
+    pv_cmd()
+        .args(["-f", "-L", "1M", "--adaptive", "--pressure-file"])
+        .arg(pressure.path())
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1.00MiB/s"));
+}
+
+#[test]
+fn test_adaptive_missing_pressure_file() {
+    pv_cmd()
+        .args([
+            "-L",
+            "1k",
+            "--adaptive",
+            "--pressure-file",
+            "/nonexistent/io",
+        ])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read pressure from"));
+}
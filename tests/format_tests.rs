@@ -186,6 +186,70 @@ fn test_format_with_line_mode() {
         .stdout(test_data);
 }
 
+#[test]
+fn test_format_bytes_and_lines_together() {
+    let test_data = "line1\nline2\nline3\n";
+
+    pv_cmd()
+        .arg("-F")
+        .arg("%{bytes} %{lines} %{line-rate}")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data);
+}
+
+#[test]
+fn test_numeric_format_lines_in_byte_mode() {
+    let test_data = "line1\nline2\nline3\n";
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-F")
+        .arg("%{bytes} %{lines}")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"(?m)^18 3$").unwrap());
+}
+
+#[test]
+fn test_numeric_format_bytes_in_line_mode() {
+    let test_data = "line1\nline2\nline3\n";
+
+    // %{bytes} means bytes even when the bar counts lines
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-l") // line mode
+        .arg("-F")
+        .arg("%{bytes} %{lines} %{line-rate}")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"(?m)^18 3 \d+$").unwrap());
+}
+
+#[test]
+fn test_format_bytes_in_line_mode_follows_units() {
+    let test_file = create_test_file(&"a".repeat(1500));
+
+    // The same units as without -l
+    pv_cmd()
+        .args(["-f", "-l", "-k", "-F", "%{bytes}"])
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1.50kB"));
+    pv_cmd()
+        .args(["-f", "-l", "-8", "-F", "%{bytes}"])
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("11.7Kibit"));
+}
+
 #[test]
 fn test_format_complex_template() {
     let test_data = "complex format test";
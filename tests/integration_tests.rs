@@ -103,6 +103,163 @@ fn test_null_terminated_lines() {
         .stdout(test_data);
 }
 
+#[test]
+fn test_custom_delimiter_crlf() {
+    let test_data = "line1\r\nline2\r\nline3\n";
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--delimiter")
+        .arg("\\r\\n")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"^2\D").unwrap()); // bare \n is not a record end
+}
+
+#[test]
+fn test_custom_delimiter_hex_escape() {
+    let test_data = "{\"a\":1}\x1e{\"a\":2}\x1e{\"a\":3}\x1e";
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--delimiter")
+        .arg("\\x1e")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"^3\D").unwrap());
+}
+
+#[test]
+fn test_custom_delimiter_split_across_inputs() {
+    // Each file is read separately, so the delimiter straddles two reads
+    let first = create_test_file("record1\r");
+    let second = create_test_file("\nrecord2\r\n");
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--delimiter")
+        .arg("\\r\\n")
+        .arg(first.path())
+        .arg(second.path())
+        .assert()
+        .success()
+        .stdout("record1\r\nrecord2\r\n")
+        .stderr(predicate::str::is_match(r"(?m)^2\D*$").unwrap());
+}
+
+#[test]
+fn test_custom_delimiter_invalid_escape() {
+    pv_cmd()
+        .arg("--delimiter")
+        .arg("\\q")
+        .write_stdin("data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid escape"));
+}
+
+#[test]
+fn test_record_modes_conflict() {
+    // Only one of them can say where records end
+    for args in [
+        &["--records", "csv", "--delimiter", ","][..],
+        &["-0", "--delimiter", "x"],
+        &["-0", "--records", "jsonl"],
+    ] {
+        pv_cmd()
+            .args(args)
+            .write_stdin("data")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn test_records_csv_quoted_newlines() {
+    let test_data = "id,note\n1,\"multi\nline\"\n2,\"say \"\"hi\"\"\"\n3,last";
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--records")
+        .arg("csv")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"(?m)^4\D*$").unwrap()); // header + 3 rows
+}
+
+#[test]
+fn test_records_jsonl_skips_blank_lines() {
+    let test_data = "{\"a\":1}\n\n{\"a\":2}\n  \n{\"a\":3}\n";
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--records")
+        .arg("jsonl")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"(?m)^3\D*$").unwrap());
+}
+
+#[test]
+fn test_records_u32be_length() {
+    let mut test_data = Vec::new();
+    for payload in [&b"hello"[..], b"", &[b'\n'; 300]] {
+        test_data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        test_data.extend_from_slice(payload);
+    }
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--records")
+        .arg("u32be-length")
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::is_match(r"(?m)^3\D*$").unwrap());
+}
+
+#[test]
+fn test_records_varint_length_split_across_inputs() {
+    // A 300 byte payload needs a two byte varint, split here between the files
+    let mut first = vec![0xac];
+    let mut second = vec![0x02];
+    second.extend_from_slice(&[b'x'; 300]);
+    second.extend_from_slice(&[0x01, b'y']);
+    let mut first_file = NamedTempFile::new().unwrap();
+    first_file.write_all(&first).unwrap();
+    let mut second_file = NamedTempFile::new().unwrap();
+    second_file.write_all(&second).unwrap();
+    first.append(&mut second);
+
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // count
+        .arg("--records")
+        .arg("varint-length")
+        .arg(first_file.path())
+        .arg(second_file.path())
+        .assert()
+        .success()
+        .stdout(first)
+        .stderr(predicate::str::is_match(r"(?m)^2\D*$").unwrap());
+}
+
 #[test]
 fn test_size_option() {
     let test_data = "test data";
@@ -0,0 +1,129 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_json_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--json"));
+}
+
+#[test]
+fn test_json_final_record() {
+    let test_data = "line1\nline2\nline3\n";
+    let test_file = create_test_file(test_data);
+
+    let output = pv_cmd()
+        .arg("--json")
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .get_output()
+        .stderr
+        .clone();
+
+    let stderr = String::from_utf8(output).unwrap();
+    let last = stderr.lines().last().unwrap();
+    assert!(last.starts_with("{\"elapsed\":"), "Not JSON: {}", last);
+    assert!(last.contains("\"bytes\":18"));
+    assert!(last.contains("\"lines\":3"));
+    assert!(last.contains("\"percent\":100.00"));
+    assert!(last.contains("\"name\":null"));
+    assert!(last.contains("\"done\":true"));
+    assert!(last.contains("\"read_errors\":0,\"write_errors\":0"));
+}
+
+#[test]
+fn test_json_unknown_size() {
+    pv_cmd()
+        .arg("--json")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stdout("test data")
+        .stderr(
+            predicate::str::contains("\"percent\":null")
+                .and(predicate::str::contains("\"eta\":null")),
+        );
+}
+
+#[test]
+fn test_json_name_is_escaped() {
+    pv_cmd()
+        .arg("--json")
+        .arg("-N")
+        .arg("my \"job\"")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(r#""name":"my \"job\"""#));
+}
+
+#[test]
+fn test_json_updates_every_interval() {
+    let test_data = "a".repeat(300);
+
+    let output = pv_cmd()
+        .arg("--json")
+        .arg("-i")
+        .arg("0.2")
+        .arg("-L")
+        .arg("400") // Takes about 0.75s
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .get_output()
+        .stderr
+        .clone();
+
+    let stderr = String::from_utf8(output).unwrap();
+    let records: Vec<&str> = stderr.lines().collect();
+    assert!(records.len() >= 3, "Too few records: {:?}", records);
+    assert!(records[..records.len() - 1]
+        .iter()
+        .all(|record| record.contains("\"done\":false")));
+    assert!(records.last().unwrap().contains("\"done\":true"));
+}
+
+#[test]
+fn test_json_quiet_mode() {
+    pv_cmd()
+        .arg("--json")
+        .arg("-q")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stdout("test data")
+        .stderr("");
+}
+
+#[test]
+fn test_json_bad_file_descriptor() {
+    pv_cmd()
+        .arg("--json=99")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to open file descriptor 99",
+        ));
+}
@@ -0,0 +1,354 @@
+use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::{NamedTempFile, TempDir};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Find a local port that is free to listen on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Make an HTTP request, retrying until the server is up
+fn http_get(port: u16, path: &str) -> String {
+    let start = Instant::now();
+    let mut stream = loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => break stream,
+            Err(_) if start.elapsed() < Duration::from_secs(5) => {
+                thread::sleep(Duration::from_millis(50))
+            }
+            Err(e) => panic!("Metrics server never came up: {}", e),
+        }
+    };
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_metrics_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--metrics-listen"))
+        .stdout(predicate::str::contains("--metrics-textfile"));
+}
+
+#[test]
+fn test_metrics_textfile() {
+    let test_file = create_test_file("line1\nline2\nline3\n");
+    let metrics_dir = TempDir::new().unwrap();
+    let metrics_path = metrics_dir.path().join("pv.prom");
+
+    pv_cmd()
+        .arg("--metrics-textfile")
+        .arg(&metrics_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("line1\nline2\nline3\n");
+
+    let metrics = fs::read_to_string(&metrics_path).unwrap();
+    assert!(metrics.contains("# TYPE pv_transferred_bytes_total counter\n"));
+    assert!(metrics.contains("\npv_transferred_bytes_total 18\n"));
+    assert!(metrics.contains("\npv_transferred_lines_total 3\n"));
+    assert!(metrics.contains("\npv_skipped_errors_total{direction=\"read\"} 0\n"));
+    assert!(metrics.contains("\npv_skipped_errors_total{direction=\"write\"} 0\n"));
+    assert!(metrics.contains("\npv_throttle_seconds_total 0.000\n"));
+    assert!(metrics.contains("\npv_expected_size_bytes 18\n"));
+    assert!(metrics.contains("\npv_average_rate_bytes_per_second "));
+}
+
+#[test]
+fn test_metrics_labelled_by_name() {
+    let metrics_dir = TempDir::new().unwrap();
+    let metrics_path = metrics_dir.path().join("pv.prom");
+
+    pv_cmd()
+        .arg("-N")
+        .arg("backup \"nightly\"")
+        .arg("--metrics-textfile")
+        .arg(&metrics_path)
+        .write_stdin("test data")
+        .assert()
+        .success();
+
+    let metrics = fs::read_to_string(&metrics_path).unwrap();
+    assert!(metrics.contains("\npv_transferred_bytes_total{name=\"backup \\\"nightly\\\"\"} 9\n"));
+    assert!(metrics.contains(
+        "\npv_skipped_errors_total{name=\"backup \\\"nightly\\\"\",direction=\"read\"} 0\n"
+    ));
+    // A pipe has no size to expect
+    assert!(!metrics.contains("pv_expected_size"));
+}
+
+#[test]
+fn test_metrics_line_mode_expected_size() {
+    let test_file = create_test_file("line1\nline2\nline3\n");
+    let metrics_dir = TempDir::new().unwrap();
+    let metrics_path = metrics_dir.path().join("pv.prom");
+
+    pv_cmd()
+        .arg("-l")
+        .arg("--metrics-textfile")
+        .arg(&metrics_path)
+        .arg(test_file.path())
+        .assert()
+        .success();
+
+    let metrics = fs::read_to_string(&metrics_path).unwrap();
+    assert!(metrics.contains("\npv_expected_size_lines 3\n"));
+    assert!(metrics.contains("\npv_transferred_lines_total 3\n"));
+}
+
+#[test]
+fn test_metrics_throttle_time() {
+    let metrics_dir = TempDir::new().unwrap();
+    let metrics_path = metrics_dir.path().join("pv.prom");
+
+    pv_cmd()
+        .arg("-L")
+        .arg("1000")
+        .arg("--metrics-textfile")
+        .arg(&metrics_path)
+        .write_stdin("a".repeat(500)) // Takes about 0.5s
+        .assert()
+        .success();
+
+    let metrics = fs::read_to_string(&metrics_path).unwrap();
+    let throttle: f64 = metrics
+        .lines()
+        .find_map(|line| line.strip_prefix("pv_throttle_seconds_total "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(throttle > 0.3, "Throttle time too short: {}", throttle);
+}
+
+#[test]
+fn test_metrics_listen() {
+    let port = free_port();
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .arg("-q")
+        .arg("-N")
+        .arg("relay")
+        .arg("--metrics-listen")
+        .arg(format!("127.0.0.1:{}", port))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Keep the transfer open while scraping
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"test data").unwrap();
+    stdin.flush().unwrap();
+    thread::sleep(Duration::from_millis(1500));
+
+    let response = http_get(port, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    assert!(response.contains("\npv_transferred_bytes_total{name=\"relay\"} 9\n"));
+
+    let response = http_get(port, "/other");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_metrics_listen_invalid_address() {
+    pv_cmd()
+        .arg("--metrics-listen")
+        .arg("not an address")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to listen on 'not an address'",
+        ));
+}
+
+#[test]
+fn test_metrics_textfile_unwritable() {
+    pv_cmd()
+        .arg("--metrics-textfile")
+        .arg("/nonexistent/dir/pv.prom")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to create metrics file"));
+}
+
+/// Collect the StatsD packets that arrive at a socket until it goes quiet
+fn statsd_packets(socket: &UdpSocket) -> Vec<String> {
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let mut packets = Vec::new();
+    let mut buf = [0; 4096];
+    while let Ok(len) = socket.recv(&mut buf) {
+        packets.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+    }
+    packets
+}
+
+#[test]
+fn test_statsd_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--statsd"))
+        .stdout(predicate::str::contains("--statsd-prefix"));
+}
+
+#[test]
+fn test_statsd_final_packet() {
+    let test_file = create_test_file("line1\nline2\nline3\n");
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    pv_cmd()
+        .arg("--statsd")
+        .arg(socket.local_addr().unwrap().to_string())
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("line1\nline2\nline3\n");
+
+    let packets = statsd_packets(&socket);
+    let last = packets.last().expect("No StatsD packets");
+    let metrics: Vec<&str> = last.lines().collect();
+    assert_eq!(metrics[0], "pv.bytes:18|c");
+    assert_eq!(metrics[1], "pv.lines:3|c");
+    assert!(metrics[2].starts_with("pv.rate:") && metrics[2].ends_with("|g"));
+    assert_eq!(metrics[3], "pv.percent:100.00|g");
+}
+
+#[test]
+fn test_statsd_counters_are_increments() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    pv_cmd()
+        .arg("--statsd")
+        .arg(socket.local_addr().unwrap().to_string())
+        .arg("-i")
+        .arg("0.2")
+        .arg("-L")
+        .arg("400")
+        .write_stdin("a".repeat(300)) // Takes about 0.75s
+        .assert()
+        .success();
+
+    let packets = statsd_packets(&socket);
+    assert!(packets.len() >= 3, "Too few packets: {:?}", packets);
+    let total: u64 = packets
+        .iter()
+        .map(|packet| {
+            let bytes = packet.lines().next().unwrap();
+            bytes
+                .strip_prefix("pv.bytes:")
+                .and_then(|count| count.strip_suffix("|c"))
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+        })
+        .sum();
+    assert_eq!(total, 300);
+    // A pipe has no size, so no percentage
+    assert!(packets.iter().all(|packet| !packet.contains("percent")));
+}
+
+#[test]
+fn test_statsd_prefix_from_name() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    pv_cmd()
+        .arg("--statsd")
+        .arg(socket.local_addr().unwrap().to_string())
+        .arg("-N")
+        .arg("nightly backup")
+        .write_stdin("test data")
+        .assert()
+        .success();
+
+    let packets = statsd_packets(&socket);
+    assert!(packets
+        .last()
+        .unwrap()
+        .starts_with("pv.nightly_backup.bytes:9|c\n"));
+}
+
+#[test]
+fn test_statsd_custom_prefix() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    pv_cmd()
+        .arg("--statsd")
+        .arg(socket.local_addr().unwrap().to_string())
+        .arg("--statsd-prefix")
+        .arg("relays.eu")
+        .arg("-N")
+        .arg("ignored")
+        .write_stdin("test data")
+        .assert()
+        .success();
+
+    let packets = statsd_packets(&socket);
+    assert!(packets.last().unwrap().starts_with("relays.eu.bytes:9|c\n"));
+}
+
+#[test]
+fn test_statsd_without_listener() {
+    // Metrics are fire-and-forget, so nobody listening is fine
+    let port = free_port();
+    pv_cmd()
+        .arg("--statsd")
+        .arg(format!("127.0.0.1:{}", port))
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stdout("test data");
+}
+
+#[test]
+fn test_statsd_invalid_address() {
+    pv_cmd()
+        .arg("--statsd")
+        .arg("no port here")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect to StatsD"));
+}
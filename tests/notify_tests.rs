@@ -0,0 +1,171 @@
+#![cfg(unix)]
+
+use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::Command;
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// A stand-in for systemd's notification socket
+fn notify_socket(dir: &TempDir) -> (UnixDatagram, std::path::PathBuf) {
+    let path = dir.path().join("notify.sock");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    (socket, path)
+}
+
+/// Collect the notifications that have arrived
+fn notifications(socket: &UnixDatagram) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut buf = [0; 4096];
+    while let Ok(len) = socket.recv(&mut buf) {
+        messages.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+    }
+    messages
+}
+
+#[test]
+fn test_notify_ready_and_final_status() {
+    let test_file = create_test_file("test data");
+    let dir = TempDir::new().unwrap();
+    let (socket, path) = notify_socket(&dir);
+
+    pv_cmd()
+        .env("NOTIFY_SOCKET", &path)
+        .arg("-b")
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("test data");
+
+    let messages = notifications(&socket);
+    assert_eq!(messages.first().map(String::as_str), Some("READY=1"));
+    let last = messages.last().unwrap();
+    assert!(last.starts_with("STATUS="), "{:?}", messages);
+    assert!(last.contains("100% 9 B/9 B"), "{:?}", messages);
+}
+
+#[test]
+fn test_notify_status_uses_format_string() {
+    let dir = TempDir::new().unwrap();
+    let (socket, path) = notify_socket(&dir);
+
+    // Notifications aren't terminal output, so -q leaves them alone
+    pv_cmd()
+        .env("NOTIFY_SOCKET", &path)
+        .arg("-q")
+        .arg("-N")
+        .arg("job")
+        .arg("-F")
+        .arg("%N %b")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr("");
+
+    let messages = notifications(&socket);
+    assert!(messages
+        .last()
+        .unwrap()
+        .starts_with("STATUS=job:  9 B\nWATCHDOG=1"));
+}
+
+#[test]
+fn test_notify_watchdog_stops_on_stall() {
+    let dir = TempDir::new().unwrap();
+    let (socket, path) = notify_socket(&dir);
+
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .env("NOTIFY_SOCKET", &path)
+        .arg("-q")
+        .arg("-i")
+        .arg("0.2")
+        .arg("--stall-after")
+        .arg("0.5")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Some data, then a stall with the input still open
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"test data").unwrap();
+    stdin.flush().unwrap();
+    thread::sleep(Duration::from_millis(1200));
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    let messages = notifications(&socket);
+    let statuses: Vec<&String> = messages
+        .iter()
+        .filter(|message| message.starts_with("STATUS="))
+        .collect();
+    assert!(statuses.len() >= 5, "Too few updates: {:?}", messages);
+    let pinged: Vec<bool> = statuses
+        .iter()
+        .map(|status| status.ends_with("\nWATCHDOG=1"))
+        .collect();
+    // Pings until the stall, none during it, and one more for finishing
+    assert!(pinged[0], "{:?}", messages);
+    assert!(!pinged[pinged.len() - 2], "{:?}", messages);
+    assert!(pinged[pinged.len() - 1], "{:?}", messages);
+}
+
+#[test]
+fn test_notify_watchdog_pinged_while_rate_limited() {
+    let dir = TempDir::new().unwrap();
+    let (socket, path) = notify_socket(&dir);
+
+    // A byte every half second, so most updates see nothing new
+    pv_cmd()
+        .env("NOTIFY_SOCKET", &path)
+        .args(["-q", "-i", "0.2", "-L", "2"])
+        .write_stdin("abcd")
+        .assert()
+        .success()
+        .stdout("abcd");
+
+    let messages = notifications(&socket);
+    let statuses: Vec<&String> = messages
+        .iter()
+        .filter(|message| message.starts_with("STATUS="))
+        .collect();
+    assert!(statuses.len() >= 5, "Too few updates: {:?}", messages);
+    assert!(
+        statuses
+            .iter()
+            .all(|status| status.ends_with("\nWATCHDOG=1")),
+        "{:?}",
+        messages
+    );
+}
+
+#[test]
+fn test_notify_missing_socket() {
+    // A socket that isn't there is ignored rather than failing the transfer
+    pv_cmd()
+        .env("NOTIFY_SOCKET", "/nonexistent/notify.sock")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stdout("test data");
+}
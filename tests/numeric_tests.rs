@@ -35,7 +35,7 @@ fn test_basic_numeric_output() {
         .assert()
         .success()
         .stdout(test_data)
-        .stderr(predicate::str::contains("0")); // Should show position/percentage
+        .stderr(predicate::str::is_match(r"^\d+").unwrap()); // Should show position/percentage
 }
 
 #[test]
@@ -108,6 +108,23 @@ fn test_numeric_with_all_flags() {
         .assert()
         .success()
         .stdout(test_data)
+        .stderr(predicate::str::is_match(r"^\d+\.\d{4} 9 \d+\.\d{4}\n$").unwrap());
+    // "time bytes rate" as raw numbers
+}
+
+#[test]
+fn test_numeric_human_with_all_flags() {
+    let test_data = "test data";
+
+    pv_cmd()
+        .arg("--numeric-human")
+        .arg("-t") // timer
+        .arg("-b") // bytes
+        .arg("-r") // rate
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
         .stderr(
             predicate::str::is_match(r"^\d+\.\d+ \d+[A-Za-z]+ \d+(?:\.\d+)?[A-Za-z]+/s").unwrap(),
         ); // "time bytes rate" with units
@@ -158,6 +175,71 @@ fn test_numeric_with_format_percentage() {
         .stderr(predicate::str::contains("Progress:"));
 }
 
+#[test]
+fn test_numeric_bytes_are_raw() {
+    let test_data = "x".repeat(2048);
+
+    // GNU pv prints a bare byte count, not 2.00KiB
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // bytes
+        .write_stdin(test_data.as_bytes())
+        .assert()
+        .success()
+        .stderr(predicate::str::ends_with("\n2048\n").or(predicate::eq("2048\n")));
+}
+
+#[test]
+fn test_numeric_gnu_field_order() {
+    let test_data = "x".repeat(100);
+    let test_file = create_test_file(&test_data);
+
+    // Elapsed time, then percentage, then rate
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-t") // timer
+        .arg("-r") // rate
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"(?m)^\d+\.\d{4} 100 \d+\.\d{4}$").unwrap());
+}
+
+#[test]
+fn test_numeric_bits_are_raw() {
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b") // bytes
+        .arg("-8") // bits
+        .write_stdin("x".repeat(100))
+        .assert()
+        .success()
+        .stderr(predicate::str::ends_with("800\n"));
+}
+
+#[test]
+fn test_numeric_human() {
+    pv_cmd()
+        .arg("--numeric-human")
+        .arg("-b") // bytes
+        .write_stdin("x".repeat(2048))
+        .assert()
+        .success()
+        .stderr(predicate::str::ends_with("2.00KiB\n"));
+}
+
+#[test]
+fn test_numeric_human_with_si_units() {
+    pv_cmd()
+        .arg("--numeric-human")
+        .arg("-k") // SI units
+        .arg("-b") // bytes
+        .write_stdin("x".repeat(1500))
+        .assert()
+        .success()
+        .stderr(predicate::str::ends_with("1.50kB\n"));
+}
+
 #[test]
 fn test_numeric_line_mode() {
     let test_data = "line1\nline2\nline3\n";
@@ -173,6 +255,45 @@ fn test_numeric_line_mode() {
         .stderr(predicate::str::contains("3")); // 3 lines
 }
 
+#[test]
+fn test_numeric_line_mode_percentage_from_file() {
+    // The file size is in bytes, so the line total has to be estimated
+    let test_data = "line\n".repeat(20_000);
+    let test_file = create_test_file(&test_data);
+
+    let output = pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-l") // line mode
+        .arg(test_file.path())
+        .output()
+        .expect("Failed to execute pv");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let percentages: Vec<u64> = stderr.lines().map(|l| l.parse().unwrap()).collect();
+    assert!(percentages.iter().all(|p| *p <= 100));
+    assert_eq!(percentages.last(), Some(&100));
+}
+
+#[test]
+fn test_numeric_line_mode_count_lines() {
+    let test_data = "line\n".repeat(20_000);
+    let test_file = create_test_file(&test_data);
+
+    let output = pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-l") // line mode
+        .arg("--count-lines")
+        .arg(test_file.path())
+        .output()
+        .expect("Failed to execute pv");
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, test_data.as_bytes());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.lines().last(), Some("100"));
+}
+
 #[test]
 fn test_numeric_with_size() {
     let test_data = "test data";
@@ -203,6 +324,162 @@ fn test_numeric_with_file_input() {
         .stderr(predicate::str::is_match(r"^\d+").unwrap());
 }
 
+#[test]
+fn test_numeric_stdin_redirected_from_file() {
+    // A regular file on stdin has a size, so a real percentage can be shown
+    let test_data = "x".repeat(1000);
+    let test_file = create_test_file(&test_data);
+
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("pv"))
+        .arg("-n") // numeric mode
+        .stdin(std::fs::File::open(test_file.path()).unwrap())
+        .output()
+        .expect("Failed to execute pv");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), test_data);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).lines().last(),
+        Some("100")
+    );
+}
+
+#[test]
+fn test_numeric_stdin_dash_counts_toward_size() {
+    let test_data = "x".repeat(500);
+    let test_file = create_test_file(&test_data);
+
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("pv"))
+        .arg("-n") // numeric mode
+        .arg(test_file.path())
+        .arg("-")
+        .stdin(std::fs::File::open(test_file.path()).unwrap())
+        .output()
+        .expect("Failed to execute pv");
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout.len(), 1000);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let percentages: Vec<u64> = stderr.lines().map(|l| l.parse().unwrap()).collect();
+    assert!(percentages.iter().all(|p| *p <= 100));
+    assert_eq!(percentages.last(), Some(&100));
+}
+
+#[test]
+fn test_numeric_stdin_pipe_has_unknown_size() {
+    let test_data = "x".repeat(1000);
+
+    // A pipe has no size, so the position is shown instead of a percentage
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .write_stdin(test_data.as_bytes())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::ends_with("1000\n"));
+}
+
+#[test]
+fn test_numeric_interval_updates_while_stalled() {
+    use std::process::{Command as StdCommand, Stdio};
+
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-n", "-b", "-i", "0.2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Hold the pipe open without writing anything
+    let mut stdin = child.stdin.take().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    stdin.write_all(b"data").unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert!(lines.len() >= 3, "Too few updates: {:?}", lines);
+    assert!(lines[0].starts_with('0'));
+    assert!(lines.last().unwrap().starts_with('4'));
+}
+
+#[test]
+fn test_numeric_rate_drops_to_zero_while_stalled() {
+    use std::process::{Command as StdCommand, Stdio};
+
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-n", "-i", "0.2", "-F", "%r %a"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Some data, then nothing for a while
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(&[b'a'; 10000]).unwrap();
+    stdin.flush().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let rates: Vec<(f64, f64)> = stderr
+        .lines()
+        .map(|line| {
+            let (rate, avg_rate) = line.split_once(' ').unwrap();
+            (rate.parse().unwrap(), avg_rate.parse().unwrap())
+        })
+        .collect();
+    assert!(rates.len() >= 3, "Too few updates: {:?}", rates);
+    // The current rate is zero once the data stops, while the average only falls
+    let (rate, avg_rate) = rates[rates.len() - 2];
+    assert_eq!(rate, 0.0, "{:?}", rates);
+    assert!(avg_rate > 0.0, "{:?}", rates);
+}
+
+#[test]
+fn test_numeric_default_interval_is_one_second() {
+    // A quick transfer finishes before the first timed update
+    pv_cmd()
+        .arg("-n") // numeric mode
+        .arg("-b")
+        .write_stdin("x".repeat(100_000))
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"^[^\n]+\n$").unwrap());
+}
+
+#[test]
+fn test_numeric_invalid_interval() {
+    pv_cmd()
+        .arg("-n")
+        .arg("-i")
+        .arg("0")
+        .write_stdin("data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Interval must be a positive number",
+        ));
+}
+
+#[test]
+fn test_numeric_interval_too_long() {
+    pv_cmd()
+        .arg("-n")
+        .arg("-i")
+        .arg("1e308")
+        .write_stdin("data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Interval is too long"));
+}
+
 #[test]
 fn test_numeric_empty_input() {
     pv_cmd()
@@ -279,3 +556,23 @@ fn test_numeric_overrides_visual_progress() {
     assert_eq!(String::from_utf8_lossy(&output.stdout), test_data);
     assert!(String::from_utf8_lossy(&output.stderr).contains("9"));
 }
+
+#[test]
+fn test_count_lines_needs_line_mode() {
+    pv_cmd()
+        .arg("--count-lines")
+        .write_stdin("line\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("-l"));
+}
+
+#[test]
+fn test_count_lines_conflicts_with_size() {
+    pv_cmd()
+        .args(["-l", "-s", "10", "--count-lines"])
+        .write_stdin("line\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
@@ -0,0 +1,349 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::io::Write;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_progress_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--progress-fd"))
+        .stdout(predicate::str::contains("--progress-file"));
+}
+
+#[test]
+fn test_progress_fd_numeric() {
+    let test_file = create_test_file("test data");
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("out.txt");
+
+    // Data goes to the -o file, leaving stdout free for progress
+    pv_cmd()
+        .arg("-n")
+        .arg("--progress-fd")
+        .arg("1")
+        .arg("-o")
+        .arg(&output_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("100\n")
+        .stderr("");
+
+    assert_eq!(fs::read_to_string(&output_path).unwrap(), "test data");
+}
+
+#[test]
+fn test_progress_fd_json() {
+    let test_file = create_test_file("test data");
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("out.txt");
+
+    pv_cmd()
+        .arg("--json")
+        .arg("--progress-fd")
+        .arg("1")
+        .arg("-o")
+        .arg(&output_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"bytes\":9").and(predicate::str::contains("\"done\":true")),
+        )
+        .stderr("");
+}
+
+#[test]
+fn test_progress_fd_bar() {
+    let test_file = create_test_file("test data");
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("out.txt");
+
+    // The bar is drawn when asked for, even though stdout isn't a terminal
+    pv_cmd()
+        .arg("--progress-fd")
+        .arg("1")
+        .arg("-o")
+        .arg(&output_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("100%").and(predicate::str::contains("\x1b").not()))
+        .stderr("");
+}
+
+#[test]
+fn test_progress_fd_invalid() {
+    pv_cmd()
+        .arg("--progress-fd")
+        .arg("99")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to open file descriptor 99",
+        ));
+}
+
+#[test]
+fn test_progress_fd_not_the_data() {
+    // Progress would be mixed in with the data going through
+    for args in [
+        &["--progress-fd", "1"][..],
+        &["--json=1"],
+        &["--progress-fd", "0", "-o", "out.txt"],
+        &["--json=0", "-o", "out.txt"],
+    ] {
+        pv_cmd()
+            .args(args)
+            .write_stdin("test data")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("File descriptor"))
+            .stdout("");
+    }
+}
+
+#[test]
+fn test_progress_file_holds_latest_numeric_line() {
+    let test_file = create_test_file("test data");
+    let status_dir = TempDir::new().unwrap();
+    let status_path = status_dir.path().join("status");
+
+    pv_cmd()
+        .arg("-n")
+        .arg("--progress-file")
+        .arg(&status_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("test data")
+        .stderr("");
+
+    // Only the final update is left, and no temporary file
+    assert_eq!(fs::read_to_string(&status_path).unwrap(), "100\n");
+    assert_eq!(fs::read_dir(status_dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_progress_file_holds_latest_json_record() {
+    let test_file = create_test_file("line1\nline2\n");
+    let status_dir = TempDir::new().unwrap();
+    let status_path = status_dir.path().join("status.json");
+
+    pv_cmd()
+        .arg("--json")
+        .arg("--progress-file")
+        .arg(&status_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr("");
+
+    let status = fs::read_to_string(&status_path).unwrap();
+    assert_eq!(status.lines().count(), 1);
+    assert!(status.contains("\"lines\":2"));
+    assert!(status.contains("\"done\":true"));
+}
+
+#[test]
+fn test_progress_file_bar() {
+    let test_file = create_test_file("test data");
+    let status_dir = TempDir::new().unwrap();
+    let status_path = status_dir.path().join("status");
+
+    pv_cmd()
+        .arg("--progress-file")
+        .arg(&status_path)
+        .arg("-b")
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr("");
+
+    let status = fs::read_to_string(&status_path).unwrap();
+    assert_eq!(status.lines().count(), 1);
+    assert!(status.contains("100"));
+    assert!(status.contains("9 B/9 B"));
+    assert!(!status.contains('\x1b'));
+}
+
+#[test]
+fn test_progress_file_spinner_without_size() {
+    let status_dir = TempDir::new().unwrap();
+    let status_path = status_dir.path().join("status");
+
+    // A pipe has no size, so there is no total, percentage or ETA to show
+    for args in [&[][..], &["-t", "-b", "-r", "-e"]] {
+        pv_cmd()
+            .arg("--progress-file")
+            .arg(&status_path)
+            .args(args)
+            .write_stdin("test data")
+            .assert()
+            .success();
+
+        let status = fs::read_to_string(&status_path).unwrap();
+        assert!(status.contains("9 B"), "{}", status);
+        assert!(!status.contains('%'), "{}", status);
+        assert!(!status.contains("9 B/"), "{}", status);
+    }
+}
+
+#[test]
+fn test_progress_file_with_separate_json_fd() {
+    let test_file = create_test_file("test data");
+    let status_dir = TempDir::new().unwrap();
+    let status_path = status_dir.path().join("status");
+
+    // JSON goes to stderr while the bar goes to the status file
+    pv_cmd()
+        .arg("--json=2")
+        .arg("--progress-file")
+        .arg(&status_path)
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("\"done\":true"));
+
+    assert!(fs::read_to_string(&status_path).unwrap().contains("100%"));
+}
+
+#[test]
+fn test_progress_file_unwritable() {
+    pv_cmd()
+        .arg("--progress-file")
+        .arg("/nonexistent/dir/status")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to create progress file"));
+}
+
+#[test]
+fn test_progress_fd_conflicts_with_progress_file() {
+    pv_cmd()
+        .arg("--progress-fd")
+        .arg("3")
+        .arg("--progress-file")
+        .arg("status")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_log_lines_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--log-lines"));
+}
+
+#[test]
+fn test_log_lines_final_line() {
+    let test_file = create_test_file("test data");
+
+    // The default interval is long, so a quick transfer only logs its completion
+    pv_cmd()
+        .arg("--log-lines")
+        .arg("-b")
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("test data")
+        .stderr(
+            predicate::str::is_match(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}Z .*100% 9 B/9 B\n$")
+                .unwrap(),
+        );
+}
+
+#[test]
+fn test_log_lines_use_format_tokens() {
+    let test_file = create_test_file("line1\nline2\n");
+
+    pv_cmd()
+        .arg("--log-lines")
+        .arg("-F")
+        .arg("%N %b %{lines}")
+        .arg("-N")
+        .arg("job")
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"Z job: +12 B/12 B 2\n$").unwrap());
+}
+
+#[test]
+fn test_force_output_to_non_terminal_logs_lines() {
+    let test_data = "a".repeat(300);
+
+    // stderr is a pipe here, so -f writes lines instead of redrawing
+    let output = pv_cmd()
+        .arg("-f")
+        .arg("-i")
+        .arg("0.2")
+        .arg("-L")
+        .arg("400") // Takes about 0.75s
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .get_output()
+        .stderr
+        .clone();
+
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(!stderr.contains('\r'), "Redraws in log: {:?}", stderr);
+    assert!(!stderr.contains('\x1b'), "Escapes in log: {:?}", stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert!(lines.len() >= 3, "Too few lines: {:?}", lines);
+    assert!(lines
+        .iter()
+        .all(|line| line.ends_with("Z") || line.contains("Z ")));
+    assert!(lines.last().unwrap().contains("300 B"));
+}
+
+#[test]
+fn test_log_lines_quiet_mode() {
+    pv_cmd()
+        .arg("--log-lines")
+        .arg("-q")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_log_lines_numeric_mode() {
+    // Numeric output is already line-based, so it is left as it is
+    pv_cmd()
+        .arg("--log-lines")
+        .arg("-n")
+        .arg("-s")
+        .arg("9")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr("100\n");
+}
@@ -0,0 +1,130 @@
+use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Time a transfer of `size` bytes
+fn time_transfer(args: &[&str], size: usize) -> f64 {
+    let test_data = "a".repeat(size);
+    let start = Instant::now();
+    pv_cmd()
+        .arg("-q")
+        .args(args)
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+    start.elapsed().as_secs_f64()
+}
+
+#[test]
+fn test_ramp_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--ramp"))
+        .stdout(predicate::str::contains("--ramp-shape"));
+}
+
+#[test]
+fn test_ramp_requires_limit() {
+    pv_cmd()
+        .args(["--ramp", "5"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--rate-limit"));
+}
+
+#[test]
+fn test_ramp_linear() {
+    // Half the limit on average over the ramp, so 10k by 2s and the rest at full rate
+    let elapsed = time_transfer(&["-L", "10k", "--ramp", "2"], 20480);
+    assert!((2.5..5.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_ramp_starts_with_the_data() {
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .args(["-q", "-L", "2k", "--ramp", "4"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+
+    // A slow producer still gets the whole ramp once it starts sending
+    thread::sleep(Duration::from_secs(3));
+    let start = Instant::now();
+    stdin.write_all(&[b'a'; 4096]).unwrap();
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    // Half the limit on average over the ramp covers the 4k in 4s
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!((3.5..6.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_ramp_exponential() {
+    // Slower to get going than a linear ramp
+    let elapsed = time_transfer(
+        &["-L", "10k", "--ramp", "2", "--ramp-shape", "exponential"],
+        20480,
+    );
+    assert!((3.2..6.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_ramp_lines() {
+    let test_data = "line\n".repeat(10);
+    let start = Instant::now();
+
+    pv_cmd()
+        .args(["-q", "-l", "-L", "5", "--ramp", "2"])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!((2.4..5.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_ramp_shows_rising_limit() {
+    let output = pv_cmd()
+        .args([
+            "-n", "-i", "0.5", "-F", "%{limit}", "-L", "10k", "--ramp", "2",
+        ])
+        .write_stdin("a".repeat(20480))
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+
+    let limits: Vec<f64> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim().parse().unwrap())
+        .collect();
+    assert!(limits.len() > 2, "{:?}", limits);
+    assert!(limits[0] < 10240.0, "{:?}", limits);
+    assert!(
+        limits.windows(2).all(|pair| pair[1] >= pair[0]),
+        "{:?}",
+        limits
+    );
+    assert_eq!(limits.last(), Some(&10240.0));
+}
@@ -0,0 +1,253 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::process::{Command as StdCommand, Stdio};
+use std::time::Instant;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// A group name no other test run shares
+fn group_name(test: &str) -> String {
+    format!("test-{}-{}", test, std::process::id())
+}
+
+#[test]
+fn test_rate_group_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--rate-group"));
+}
+
+#[test]
+fn test_rate_group_requires_limit() {
+    pv_cmd()
+        .args(["--rate-group", "backups"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--rate-limit"));
+}
+
+#[test]
+fn test_rate_group_refuses_changing_limits() {
+    for args in [
+        &["--rate-schedule", "* 1k"][..],
+        &["--ramp", "1"][..],
+        &["--adaptive"][..],
+    ] {
+        pv_cmd()
+            .args(["-L", "2k", "--rate-group", "backups"])
+            .args(args)
+            .write_stdin("test")
+            .assert()
+            .failure()
+            .code(2)
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn test_rate_group_alone_gets_whole_limit() {
+    let test_data = "a".repeat(2048);
+    let start = Instant::now();
+
+    pv_cmd()
+        .args(["-q", "-L", "2k", "--rate-group", &group_name("alone")])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!((0.5..1.8).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_group_shares_limit() {
+    let group = group_name("shared");
+    let start = Instant::now();
+
+    // Each alone would take 1.5s, but together they share 2k/s
+    let children: Vec<_> = (0..2)
+        .map(|_| {
+            let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+                .args(["-q", "-L", "2k", "--rate-group", &group])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap();
+            let mut stdin = child.stdin.take().unwrap();
+            std::io::Write::write_all(&mut stdin, &[b'a'; 3072]).unwrap();
+            child
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!(elapsed >= 2.2, "Took {:.2}s", elapsed);
+}
+
+/// Start a member of `group` that sends nothing until its input is closed
+fn spawn_idle(args: &[&str], group: &str) -> (std::process::Child, std::process::ChildStdin) {
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-q", "--rate-group", group])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take().unwrap();
+    // Give it time to join
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    (child, stdin)
+}
+
+#[test]
+fn test_rate_group_members_need_the_same_limit() {
+    let group = group_name("mismatch");
+    let (mut child, stdin) = spawn_idle(&["-L", "4k"], &group);
+
+    // Together they would go over either limit
+    pv_cmd()
+        .args(["-q", "-L", "400", "--rate-group", &group])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the group's rate limit is 4096 bytes a second, not 400 bytes",
+        ));
+
+    // Once the group is empty, the next member sets the limit
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+    pv_cmd()
+        .args(["-q", "-L", "400", "--rate-group", &group])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stdout("test");
+}
+
+#[test]
+fn test_rate_group_members_count_the_same_unit() {
+    let group = group_name("unit");
+    let (mut child, stdin) = spawn_idle(&["-L", "100"], &group);
+
+    // A hundred lines a second is no match for a hundred bytes
+    pv_cmd()
+        .args(["-q", "-l", "-L", "100", "--rate-group", &group])
+        .write_stdin("test\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the group's rate limit is 100 bytes a second, not 100 lines",
+        ));
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_rate_group_names_kept_apart() {
+    let group = group_name("apart");
+    let (mut child, stdin) = spawn_idle(&["-L", "4k"], &format!("{group}.x"));
+
+    // A name that would look the same once made safe for a file
+    pv_cmd()
+        .args(["-q", "-L", "400", "--rate-group", &format!("{group}_x")])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stdout("test");
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_rate_group_paces_lines() {
+    let test_data = "line\n".repeat(2000);
+    let start = Instant::now();
+
+    pv_cmd()
+        .args([
+            "-q",
+            "-l",
+            "-L",
+            "1000",
+            "--rate-group",
+            &group_name("lines"),
+        ])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!((1.5..3.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_group_idle_member_leaves_its_share() {
+    let group = group_name("idle");
+    let (mut child, stdin) = spawn_idle(&["-L", "2k"], &group);
+    let test_data = "a".repeat(2048);
+    let start = Instant::now();
+
+    // Sending alone at the full limit, not half of it
+    pv_cmd()
+        .args(["-q", "-L", "2k", "--rate-group", &group])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    let elapsed = start.elapsed().as_secs_f64();
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+    assert!((0.5..1.6).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rate_group_refuses_shared_directory() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    let uid = StdCommand::new("id").arg("-u").output().unwrap().stdout;
+    let uid = String::from_utf8(uid).unwrap();
+    let groups = format!("pv-rate-groups-{}", uid.trim());
+
+    // Anyone could have put files in it
+    let runtime = tempfile::tempdir().unwrap();
+    std::fs::create_dir(runtime.path().join(&groups)).unwrap();
+    std::fs::set_permissions(
+        runtime.path().join(&groups),
+        std::fs::Permissions::from_mode(0o777),
+    )
+    .unwrap();
+    pv_cmd()
+        .env("XDG_RUNTIME_DIR", runtime.path())
+        .args(["-q", "-L", "1k", "--rate-group", "shared"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a directory private"));
+
+    // Or pointed it somewhere else
+    let runtime = tempfile::tempdir().unwrap();
+    let victim = tempfile::tempdir().unwrap();
+    symlink(victim.path(), runtime.path().join(&groups)).unwrap();
+    pv_cmd()
+        .env("XDG_RUNTIME_DIR", runtime.path())
+        .args(["-q", "-L", "1k", "--rate-group", "shared"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a directory private"));
+    assert_eq!(std::fs::read_dir(victim.path()).unwrap().count(), 0);
+}
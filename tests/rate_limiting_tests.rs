@@ -141,6 +141,40 @@ fn test_rate_limit_line_mode() {
     );
 }
 
+#[test]
+fn test_rate_limit_line_mode_is_smooth() {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command as StdCommand, Stdio};
+
+    let test_file = create_test_file("line\n".repeat(10).as_bytes());
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-q", "-l", "-L", "10"]) // 10 lines per second
+        .arg(test_file.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Lines should trickle out one at a time rather than in one burst
+    let start_time = Instant::now();
+    let arrivals: Vec<f64> = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map(|_| start_time.elapsed().as_secs_f64())
+        .collect();
+    assert!(child.wait().unwrap().success());
+
+    assert_eq!(arrivals.len(), 10);
+    assert!(
+        arrivals[4] - arrivals[0] >= 0.3,
+        "Lines arrived in a burst: {:?}",
+        arrivals
+    );
+    assert!(
+        arrivals.windows(2).all(|pair| pair[1] - pair[0] < 0.5),
+        "Lines arrived with long gaps: {:?}",
+        arrivals
+    );
+}
+
 #[test]
 fn test_rate_limit_invalid_suffix() {
     let mut cmd = pv_cmd();
@@ -204,3 +238,126 @@ fn test_rate_limit_very_high() {
 
     // Should complete very quickly with no effective rate limiting
 }
+
+#[test]
+fn test_rate_limit_bytes_are_smooth() {
+    use std::io::Read;
+    use std::process::{Command as StdCommand, Stdio};
+
+    // Less than one read buffer, so the old limiter wrote it all at once
+    let test_file = create_test_file(&create_test_data(20 * 1024));
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-q", "-L", "10k"])
+        .arg(test_file.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let start_time = Instant::now();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut buf = [0; 65536];
+    let mut arrivals = vec![];
+    loop {
+        match stdout.read(&mut buf).unwrap() {
+            0 => break,
+            len => arrivals.push((start_time.elapsed().as_secs_f64(), len)),
+        }
+    }
+    assert!(child.wait().unwrap().success());
+
+    let total: usize = arrivals.iter().map(|(_, len)| len).sum();
+    assert_eq!(total, 20 * 1024);
+    // About a second's worth arrives in the first half second, not everything
+    let early: usize = arrivals
+        .iter()
+        .filter(|(time, _)| *time < 0.5)
+        .map(|(_, len)| len)
+        .sum();
+    assert!(early < 10 * 1024, "Arrived in a burst: {:?}", arrivals);
+    assert!(
+        arrivals.last().unwrap().0 >= 1.5,
+        "Finished too quickly: {:?}",
+        arrivals
+    );
+}
+
+#[test]
+fn test_rate_limit_burst() {
+    let test_data = create_test_data(4096);
+    let start_time = Instant::now();
+
+    // The whole input fits in the burst, so nothing waits
+    pv_cmd()
+        .args(["-q", "-L", "1k", "--burst", "4k"])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    let elapsed = start_time.elapsed();
+    assert!(
+        elapsed.as_secs_f64() < 1.5,
+        "Burst was held back: {:.2}s",
+        elapsed.as_secs_f64()
+    );
+}
+
+#[test]
+fn test_rate_limit_small_burst_still_limits() {
+    let test_data = create_test_data(2048);
+    let start_time = Instant::now();
+
+    pv_cmd()
+        .args(["-q", "-L", "1k", "--burst", "1k"])
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+
+    // The first kilobyte goes at once, and the second at the rate
+    let elapsed = start_time.elapsed();
+    assert!(
+        (0.8..2.5).contains(&elapsed.as_secs_f64()),
+        "Took {:.2}s",
+        elapsed.as_secs_f64()
+    );
+}
+
+#[test]
+fn test_burst_requires_rate_limit() {
+    pv_cmd()
+        .args(["--burst", "4k"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--rate-limit"));
+}
+
+#[test]
+fn test_rate_limit_no_catch_up_after_stall() {
+    use std::process::{Command as StdCommand, Stdio};
+
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("pv"))
+        .args(["-q", "-L", "2k"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // A second's worth, then a stall long enough to owe two seconds more
+    let start_time = Instant::now();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(&create_test_data(2048)).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    stdin.write_all(&create_test_data(4096)).unwrap();
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    // The second part still goes at the rate rather than all at once
+    let elapsed = start_time.elapsed();
+    assert!(
+        elapsed.as_secs_f64() >= 3.5,
+        "Caught up after the stall: {:.2}s",
+        elapsed.as_secs_f64()
+    );
+}
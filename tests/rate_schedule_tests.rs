@@ -0,0 +1,200 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tempfile::NamedTempFile;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Time a transfer of `size` bytes
+fn time_transfer(args: &[&str], size: usize) -> f64 {
+    let test_data = "a".repeat(size);
+    let start = Instant::now();
+    pv_cmd()
+        .arg("-q")
+        .args(args)
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+    start.elapsed().as_secs_f64()
+}
+
+#[test]
+fn test_rate_schedule_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--rate-schedule"));
+}
+
+#[test]
+fn test_rate_schedule_limits() {
+    let elapsed = time_transfer(&["--rate-schedule", "Mon-Sun 00:00-24:00 1k"], 2048);
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_schedule_unlimited_overrides_rate_limit() {
+    let elapsed = time_transfer(&["-L", "10", "--rate-schedule", "* unlimited"], 1000);
+    assert!(elapsed < 1.5, "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_schedule_falls_back_to_rate_limit() {
+    // An empty time range never matches
+    let elapsed = time_transfer(
+        &["-L", "1k", "--rate-schedule", "00:00-00:00 unlimited"],
+        2048,
+    );
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_schedule_first_match_wins() {
+    let elapsed = time_transfer(&["--rate-schedule", "* unlimited; * 10"], 1000);
+    assert!(elapsed < 1.5, "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_schedule_file() {
+    let schedule = create_test_file(
+        b"# Quiet all week\nSat,Sun 1k\nMon-Fri 00:00-12:00 1k\nMon-Fri 12:00-24:00 1k\n",
+    );
+    let elapsed = time_transfer(
+        &[
+            "--rate-schedule",
+            &format!("@{}", schedule.path().to_str().unwrap()),
+        ],
+        2048,
+    );
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_schedule_missing_file() {
+    pv_cmd()
+        .args(["--rate-schedule", "@/nonexistent/schedule"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Failed to read '/nonexistent/schedule'",
+        ));
+}
+
+#[test]
+fn test_rate_schedule_overnight_range() {
+    // Two ranges across midnight cover the whole day between them
+    let elapsed = time_transfer(
+        &[
+            "--rate-schedule",
+            "12:00-00:00 1k; 00:00-12:00 1k; * unlimited",
+        ],
+        2048,
+    );
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_limit_format_token() {
+    pv_cmd()
+        .args(["-f", "-F", "limit %{limit}", "--rate-schedule", "* 1M"])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("limit 1.00MiB/s"));
+}
+
+#[test]
+fn test_limit_format_token_unlimited() {
+    pv_cmd()
+        .args([
+            "-f",
+            "-F",
+            "limit %{limit}",
+            "--rate-schedule",
+            "* unlimited",
+        ])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("limit unlimited"));
+}
+
+#[test]
+fn test_limit_numeric() {
+    pv_cmd()
+        .args(["-n", "-F", "%{limit}", "-L", "1M"])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1048576.0000"));
+}
+
+#[test]
+fn test_rate_schedule_invalid_day() {
+    pv_cmd()
+        .args(["--rate-schedule", "Mon-Fry 20M"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid day: Fry"));
+}
+
+#[test]
+fn test_rate_schedule_invalid_time() {
+    pv_cmd()
+        .args(["--rate-schedule", "08:00-25:00 20M"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid time: 25:00"));
+}
+
+#[test]
+fn test_rate_schedule_huge_hour() {
+    pv_cmd()
+        .args(["--rate-schedule", "99999999:00-10:00 1M"])
+        .write_stdin("test")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid time: 99999999:00"));
+}
+
+#[test]
+fn test_rate_schedule_overnight_belongs_to_start_day() {
+    const DAY: u64 = 24 * 60;
+    let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let (day, minute) = (now.as_secs() / 86400, now.as_secs() / 60 % DAY);
+    // A range from late last night until just after now, with room to spare
+    if minute + 2 >= DAY - 1 {
+        return;
+    }
+    let end = minute + 2;
+    let (today, yesterday) = (days[(day as usize + 3) % 7], days[(day as usize + 2) % 7]);
+    let hours = format!("23:59-{:02}:{:02}", end / 60, end % 60);
+    let schedule = format!("{today} {hours} 1k; {yesterday} {hours} unlimited; * 1k");
+    pv_cmd()
+        .env("TZ", "UTC")
+        .args(["-f", "-F", "limit %{limit}", "--rate-schedule", &schedule])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("limit unlimited"));
+}
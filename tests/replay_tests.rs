@@ -0,0 +1,114 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_replay_timing_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--replay-timing"));
+}
+
+#[test]
+fn test_replay_csv_timing() {
+    let test_file = create_test_file(b"0123456789abcdef");
+    let trace = create_test_file(
+        b"time,bytes,read_latency,write_latency,throttle,position\n\
+          0.000100,4,0.000010,0.000010,0.000000,4\n\
+          0.300000,4,0.000010,0.000010,0.000000,8\n\
+          0.600000,4,0.000010,0.000010,0.000000,12\n",
+    );
+
+    let start = Instant::now();
+    pv_cmd()
+        .arg("-q")
+        .arg("--replay-timing")
+        .arg(trace.path())
+        .arg(test_file.path())
+        .assert()
+        .success()
+        // Data past the end of the recording still goes through
+        .stdout("0123456789abcdef");
+    assert!(start.elapsed() >= Duration::from_millis(550));
+}
+
+#[test]
+fn test_replay_ndjson_timing() {
+    let test_file = create_test_file(b"0123456789");
+    let trace = create_test_file(
+        b"{\"time\":0.000100,\"bytes\":5,\"read_latency\":0.000010,\"write_latency\":0.000010,\"throttle\":0.000000,\"position\":5}\n\
+          {\"time\":0.400000,\"bytes\":5,\"read_latency\":0.000010,\"write_latency\":0.000010,\"throttle\":0.000000,\"position\":10}\n",
+    );
+
+    let start = Instant::now();
+    pv_cmd()
+        .arg("-q")
+        .arg("--replay-timing")
+        .arg(trace.path())
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stdout("0123456789");
+    assert!(start.elapsed() >= Duration::from_millis(350));
+}
+
+#[test]
+fn test_replay_counts_as_throttle() {
+    let test_file = create_test_file(b"0123456789");
+    let trace = create_test_file(b"0.000100,5\n0.400000,5\n");
+
+    pv_cmd()
+        .arg("--wait-summary")
+        .arg("--replay-timing")
+        .arg(trace.path())
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"rate limit 0\.[3-9]\d\ds").unwrap());
+}
+
+#[test]
+fn test_replay_missing_file() {
+    let test_file = create_test_file(b"data");
+
+    pv_cmd()
+        .arg("-q")
+        .arg("--replay-timing")
+        .arg("/nonexistent/trace.csv")
+        .arg(test_file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read replay file"));
+}
+
+#[test]
+fn test_replay_invalid_row() {
+    let test_file = create_test_file(b"data");
+    let trace = create_test_file(b"time,bytes\nsoon,4\n");
+
+    pv_cmd()
+        .arg("-q")
+        .arg("--replay-timing")
+        .arg(trace.path())
+        .arg(test_file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("line 2 is not a trace row"));
+}
@@ -192,7 +192,7 @@ fn test_rate_limiting_with_bits_mode() {
     let output = pv_cmd()
         .arg("-8")
         .arg("-L")
-        .arg("8k") // 8000 bytes/sec (should display as 64000 bits/sec)
+        .arg("8k") // 8 Kibit/s, in the same bits the display uses
         .arg("-q")
         .arg(test_file.path())
         .assert()
@@ -290,3 +290,83 @@ fn test_empty_file_with_bits_mode() {
 
     assert_eq!(String::from_utf8(output).unwrap(), "");
 }
+
+/// Time a rate-limited transfer of `size` bytes
+fn time_transfer(args: &[&str], size: usize) -> f64 {
+    let test_data = "a".repeat(size);
+    let start = std::time::Instant::now();
+    pv_cmd()
+        .arg("-q")
+        .args(args)
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data);
+    start.elapsed().as_secs_f64()
+}
+
+#[test]
+fn test_rate_limit_in_bits() {
+    // 8 kbit/s is 1000 bytes a second
+    let elapsed = time_transfer(&["-L", "8kbit"], 2000);
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_limit_follows_bits_mode() {
+    // With -8 a plain rate is in bits too, so 8k is 1024 bytes a second
+    let elapsed = time_transfer(&["-8", "-L", "8k"], 2048);
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_limit_fractional() {
+    let elapsed = time_transfer(&["-L", "1.5k"], 3072);
+    assert!((1.5..4.0).contains(&elapsed), "Took {:.2}s", elapsed);
+}
+
+#[test]
+fn test_rate_limit_prefixes() {
+    for rate in ["100Mbit", "12.5M", "12.5MB", "2MiB", "2MiB/s", "1mb", "1Gb"] {
+        pv_cmd()
+            .args(["-q", "-L", rate])
+            .write_stdin("test")
+            .assert()
+            .success()
+            .stdout("test");
+    }
+}
+
+#[test]
+fn test_rate_limit_units_need_bytes() {
+    pv_cmd()
+        .args(["-l", "-L", "1MB"])
+        .write_stdin("test\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("don't apply when counting lines"));
+}
+
+#[test]
+fn test_bar_uses_si_units() {
+    let test_file = create_test_file(&"a".repeat(1500));
+
+    pv_cmd()
+        .args(["-f", "-k", "-b"])
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1.50kB"));
+}
+
+#[test]
+fn test_bar_uses_bits() {
+    let test_file = create_test_file(&"a".repeat(1250));
+
+    pv_cmd()
+        .args(["-f", "-8", "-k", "-b"])
+        .arg(test_file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("10.0kbit"));
+}
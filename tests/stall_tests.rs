@@ -0,0 +1,152 @@
+use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Start pv on some data, then keep its input open without sending more
+fn spawn_stalled(args: &[&str]) -> (Child, std::process::ChildStdin) {
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"test data").unwrap();
+    stdin.flush().unwrap();
+    (child, stdin)
+}
+
+#[test]
+fn test_stall_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--stall-after"))
+        .stdout(predicate::str::contains("--stall-timeout"));
+}
+
+#[test]
+fn test_stall_timeout_exits() {
+    let start = Instant::now();
+    let (child, stdin) = spawn_stalled(&["--stall-timeout", "0.5"]);
+
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+    assert_eq!(output.status.code(), Some(124));
+    assert!(start.elapsed() < Duration::from_secs(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("no data transferred for 00:00:00, giving up"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_stall_timeout_passes_on_what_was_read() {
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .args(["--json", "--stall-timeout", "0.5"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"hello world\n").unwrap();
+    stdin.flush().unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+    assert_eq!(output.status.code(), Some(124));
+    assert_eq!(output.stdout, b"hello world\n");
+    // The final record still goes out
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(r#""bytes":12"#), "{}", stderr);
+    assert!(stderr.contains(r#""done":true"#), "{}", stderr);
+}
+
+#[test]
+fn test_stall_timeout_not_reached_while_data_flows() {
+    // Data arrives every few milliseconds, well inside the timeout
+    pv_cmd()
+        .arg("--stall-timeout")
+        .arg("0.5")
+        .arg("-L")
+        .arg("400")
+        .write_stdin("a".repeat(300)) // Takes about 0.75s
+        .assert()
+        .success()
+        .stdout("a".repeat(300));
+}
+
+#[test]
+fn test_stall_log_line() {
+    let (child, stdin) = spawn_stalled(&["--log-lines", "-i", "0.2", "--stall-after", "0.5"]);
+    thread::sleep(Duration::from_millis(1200));
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert!(
+        lines.iter().any(|line| line.ends_with(" STALLED 00:00:00")),
+        "{:?}",
+        lines
+    );
+    assert!(!lines[0].contains("STALLED"), "{:?}", lines);
+    // Finishing isn't a stall
+    assert!(!lines.last().unwrap().contains("STALLED"), "{:?}", lines);
+}
+
+#[test]
+fn test_stall_format_token_numeric() {
+    let (child, stdin) =
+        spawn_stalled(&["-n", "-i", "0.5", "-F", "%{stall}", "--stall-after", "1"]);
+    thread::sleep(Duration::from_millis(2300));
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let values: Vec<&str> = stderr.lines().collect();
+    assert_eq!(values[0], "0", "{:?}", values);
+    assert!(values.contains(&"2"), "{:?}", values);
+}
+
+#[test]
+fn test_stall_invalid_timeout() {
+    pv_cmd()
+        .arg("--stall-timeout")
+        .arg("0")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("positive number of seconds"));
+}
+
+#[test]
+fn test_stall_intervals_too_long() {
+    for option in ["--stall-after", "--stall-timeout"] {
+        pv_cmd()
+            .arg(option)
+            .arg("1e300")
+            .write_stdin("test data")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Interval is too long"));
+    }
+}
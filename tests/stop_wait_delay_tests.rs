@@ -157,6 +157,33 @@ fn test_wait_for_first_byte_basic() {
         .stdout(test_data);
 }
 
+#[test]
+fn test_wait_for_first_byte_hides_output_without_data() {
+    pv_cmd()
+        .arg("-W") // Wait for first byte
+        .arg("-n") // Numeric mode
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn test_wait_for_first_byte_shows_output_with_data() {
+    let test_data = "test data";
+
+    pv_cmd()
+        .arg("-W") // Wait for first byte
+        .arg("-n") // Numeric mode
+        .arg("-b")
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr(predicate::str::contains("9"));
+}
+
 #[test]
 fn test_delay_start_basic() {
     let test_data = "test data";
@@ -164,7 +191,7 @@ fn test_delay_start_basic() {
     let start = Instant::now();
     pv_cmd()
         .arg("-D")
-        .arg("0.1") // 100ms delay
+        .arg("5") // 5s delay
         .arg("-q") // Quiet mode
         .write_stdin(test_data)
         .assert()
@@ -172,8 +199,54 @@ fn test_delay_start_basic() {
         .stdout(test_data);
 
     let elapsed = start.elapsed();
-    // Should take at least 100ms due to delay
-    assert!(elapsed.as_millis() >= 90); // Allow some tolerance
+    // The delay only holds back the display, never the data
+    assert!(elapsed.as_secs_f64() < 3.0);
+}
+
+#[test]
+fn test_delay_start_hides_numeric_output() {
+    let test_data = "test data";
+
+    // The transfer finishes before the delay is up, so nothing is shown
+    pv_cmd()
+        .arg("-D")
+        .arg("5")
+        .arg("-n") // Numeric mode
+        .write_stdin(test_data)
+        .assert()
+        .success()
+        .stdout(test_data)
+        .stderr("");
+}
+
+#[test]
+fn test_delay_start_shows_numeric_output_after_delay() {
+    let test_data = "a".repeat(300);
+
+    let output = pv_cmd()
+        .arg("-D")
+        .arg("0.5")
+        .arg("-L")
+        .arg("200") // Takes about 1.5s
+        .arg("-n") // Numeric mode
+        .arg("-b")
+        .write_stdin(test_data.clone())
+        .assert()
+        .success()
+        .stdout(test_data)
+        .get_output()
+        .stderr
+        .clone();
+
+    // Updates resume once the delay is up, while the transfer is still running
+    let stderr = String::from_utf8(output).unwrap();
+    let counts: Vec<u64> = stderr
+        .lines()
+        .map(|line| line.trim().parse().unwrap())
+        .collect();
+    assert!(counts.len() >= 2, "{:?}", counts);
+    assert!(counts[0] < 300, "{:?}", counts);
+    assert_eq!(counts.last(), Some(&300), "{:?}", counts);
 }
 
 #[test]
@@ -190,6 +263,22 @@ fn test_delay_start_zero() {
         .stdout(test_data);
 }
 
+#[test]
+fn test_delay_start_invalid() {
+    for (delay, error) in [
+        ("-1", "positive number of seconds"),
+        ("inf", "positive number of seconds"),
+        ("1e20", "too long"),
+    ] {
+        pv_cmd()
+            .arg(format!("--delay={delay}"))
+            .write_stdin("")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(error));
+    }
+}
+
 #[test]
 fn test_wait_and_delay_combined() {
     let test_data = "test data";
@@ -198,7 +287,7 @@ fn test_wait_and_delay_combined() {
     pv_cmd()
         .arg("-W") // Wait for first byte
         .arg("-D")
-        .arg("0.1") // Plus 100ms delay
+        .arg("5") // Plus 5s delay
         .arg("-q")
         .write_stdin(test_data)
         .assert()
@@ -206,8 +295,8 @@ fn test_wait_and_delay_combined() {
         .stdout(test_data);
 
     let elapsed = start.elapsed();
-    // Should take at least 100ms due to delay
-    assert!(elapsed.as_millis() >= 90);
+    // Neither option holds back the data
+    assert!(elapsed.as_secs_f64() < 3.0);
 }
 
 #[test]
@@ -0,0 +1,141 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::io::Write;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_trace_options_exist() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--trace"))
+        .stdout(predicate::str::contains("--trace-format"));
+}
+
+#[test]
+fn test_trace_csv() {
+    // Big enough to take several chunks
+    let test_file = create_test_file(&vec![b'x'; 200_000]);
+    let trace_dir = TempDir::new().unwrap();
+    let trace_path = trace_dir.path().join("trace.csv");
+
+    pv_cmd()
+        .arg("-q")
+        .arg("--trace")
+        .arg(&trace_path)
+        .arg(test_file.path())
+        .assert()
+        .success();
+
+    let trace = fs::read_to_string(&trace_path).unwrap();
+    let mut rows = trace.lines();
+    assert_eq!(
+        rows.next(),
+        Some("time,bytes,read_latency,write_latency,throttle,position")
+    );
+    let rows: Vec<Vec<f64>> = rows
+        .map(|row| row.split(',').map(|field| field.parse().unwrap()).collect())
+        .collect();
+    assert!(rows.len() > 1, "{}", trace);
+    assert!(rows.iter().all(|row| row.len() == 6));
+
+    // Bytes add up to the position, and times only go forward
+    let mut total = 0.0;
+    for pair in rows.windows(2) {
+        assert!(pair[1][0] >= pair[0][0]);
+    }
+    for row in &rows {
+        total += row[1];
+        assert_eq!(row[5], total);
+    }
+    assert_eq!(total, 200_000.0);
+}
+
+#[test]
+fn test_trace_ndjson() {
+    let trace_dir = TempDir::new().unwrap();
+    let trace_path = trace_dir.path().join("trace.ndjson");
+
+    pv_cmd()
+        .arg("-q")
+        .arg("--trace")
+        .arg(&trace_path)
+        .arg("--trace-format")
+        .arg("ndjson")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stdout("test data");
+
+    let trace = fs::read_to_string(&trace_path).unwrap();
+    assert!(
+        predicate::str::is_match(
+            r#"^\{"time":\d+\.\d{6},"bytes":9,"read_latency":\d+\.\d{6},"write_latency":\d+\.\d{6},"throttle":0\.000000,"position":9\}\n$"#
+        )
+        .unwrap()
+        .eval(&trace),
+        "{}",
+        trace
+    );
+}
+
+#[test]
+fn test_trace_records_throttle() {
+    let trace_dir = TempDir::new().unwrap();
+    let trace_path = trace_dir.path().join("trace.csv");
+
+    pv_cmd()
+        .arg("-q")
+        .arg("-L")
+        .arg("1000")
+        .arg("--trace")
+        .arg(&trace_path)
+        .write_stdin("a".repeat(500)) // Takes about 0.5s
+        .assert()
+        .success();
+
+    let trace = fs::read_to_string(&trace_path).unwrap();
+    let throttle: f64 = trace
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').nth(4).unwrap().parse::<f64>().unwrap())
+        .sum();
+    assert!(throttle > 0.3, "{}", trace);
+}
+
+#[test]
+fn test_trace_format_requires_trace() {
+    pv_cmd()
+        .arg("--trace-format")
+        .arg("ndjson")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--trace <FILE>"));
+}
+
+#[test]
+fn test_trace_unwritable() {
+    pv_cmd()
+        .arg("--trace")
+        .arg("/nonexistent/dir/trace.csv")
+        .write_stdin("test data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to create trace file"));
+}
@@ -0,0 +1,188 @@
+use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+/// Helper function to create a test binary command
+fn pv_cmd() -> Command {
+    Command::cargo_bin("pv").unwrap()
+}
+
+/// Helper function to create test data
+fn create_test_file(content: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Pull the percentage for one kind of wait out of the summary line
+fn summary_percent(stderr: &str, what: &str) -> f64 {
+    let summary = stderr
+        .lines()
+        .find(|line| line.starts_with("Waited on"))
+        .unwrap_or_else(|| panic!("No summary in {:?}", stderr));
+    let after = &summary[summary.find(what).unwrap() + what.len()..];
+    let open = after.find('(').unwrap();
+    let close = after.find("%)").unwrap();
+    after[open + 1..close].parse().unwrap()
+}
+
+#[test]
+fn test_wait_summary_option_exists() {
+    pv_cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--wait-summary"));
+}
+
+#[test]
+fn test_wait_summary_format() {
+    pv_cmd()
+        .arg("--wait-summary")
+        .arg("-n")
+        .arg("-s")
+        .arg("9")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr(
+            predicate::str::is_match(
+                r"^100\nWaited on input \d+\.\d{3}s \(\d+\.\d%\), output \d+\.\d{3}s \(\d+\.\d%\), rate limit 0\.000s \(0\.0%\)\n$",
+            )
+            .unwrap(),
+        );
+}
+
+#[test]
+fn test_wait_summary_blocked_on_input() {
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .arg("--wait-summary")
+        .arg("-q")
+        .arg("-f")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"test data").unwrap();
+    stdin.flush().unwrap();
+    thread::sleep(Duration::from_millis(800));
+    drop(stdin);
+
+    // -q leaves out the summary too
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .arg("--wait-summary")
+        .arg("-n")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"test data").unwrap();
+    stdin.flush().unwrap();
+    thread::sleep(Duration::from_millis(800));
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(summary_percent(&stderr, "input") > 70.0, "{}", stderr);
+    assert!(summary_percent(&stderr, "output") < 20.0, "{}", stderr);
+}
+
+#[test]
+fn test_wait_summary_blocked_on_output() {
+    // Much more than a pipe holds, so pv has to wait for the reader
+    let test_file = create_test_file(&vec![0; 4 * 1024 * 1024]);
+    let mut child = std::process::Command::cargo_bin("pv")
+        .unwrap()
+        .arg("--wait-summary")
+        .arg("-n")
+        .arg(test_file.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(800));
+    let mut sink = Vec::new();
+    child.stdout.take().unwrap().read_to_end(&mut sink).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(sink.len(), 4 * 1024 * 1024);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(summary_percent(&stderr, "output") > 70.0, "{}", stderr);
+    assert!(summary_percent(&stderr, "input") < 20.0, "{}", stderr);
+}
+
+#[test]
+fn test_wait_summary_rate_limit() {
+    let output = pv_cmd()
+        .arg("--wait-summary")
+        .arg("-n")
+        .arg("-L")
+        .arg("1000")
+        .write_stdin("a".repeat(500)) // Takes about 0.5s
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(summary_percent(&stderr, "rate limit") > 70.0, "{}", stderr);
+}
+
+#[test]
+fn test_wait_format_tokens_numeric() {
+    let output = pv_cmd()
+        .arg("-n")
+        .arg("-F")
+        .arg("%{read-wait} %{write-wait} %{throttle-wait}")
+        .arg("-L")
+        .arg("1000")
+        .write_stdin("a".repeat(500))
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+
+    let stderr = String::from_utf8(output).unwrap();
+    let last: Vec<u64> = stderr
+        .lines()
+        .last()
+        .unwrap()
+        .split(' ')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert_eq!(last.len(), 3);
+    assert!(last[0] < 20 && last[1] < 20, "{:?}", last);
+    assert!(last[2] > 70, "{:?}", last);
+}
+
+#[test]
+fn test_wait_format_tokens_in_bar() {
+    pv_cmd()
+        .arg("--log-lines")
+        .arg("-F")
+        .arg("in %{read-wait} out %{write-wait} limit %{throttle-wait}")
+        .write_stdin("test data")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"Z in \d+% out \d+% limit 0%\n$").unwrap());
+}